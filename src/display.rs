@@ -1,9 +1,17 @@
+mod bitmap;
+mod color;
 mod display_epaper;
+mod display_epaper_grayscale;
+mod display_epaper_tricolor;
 mod display_oled;
 mod display_oled_wide;
+mod format;
 
 pub trait Display<Color: Copy + Debug> {
     const PIXEL_PER_BYTE: usize;
+    /// The layout of the bytes in [`Display::set_frame_memory_from_raw`] and
+    /// [`Display::set_frame_memory_from_scanlines`]
+    type NativeFormat: NativeFormat;
     /// The width of the display in pixel
     const WIDTH: usize;
     /// The height of the display in pixel
@@ -26,6 +34,49 @@ pub trait Display<Color: Copy + Debug> {
         image_height: usize,
     );
 
+    /// This calls the callback function for each line of the given area with
+    /// a buffer, which has to be filled with the packed bytes of that line.
+    /// This is a lot cheaper than calling a function for every pixel. The
+    /// bytes are in the layout of the display, [`Display::PIXEL_PER_BYTE`]
+    /// pixels each, and the driver documents what a line is and how the
+    /// pixels are packed. The area can be aligned to whole bytes. The callback
+    /// can be called more than once for the same line and has to fill it the
+    /// same way each time.
+    fn set_frame_memory_from_scanlines(
+        &mut self,
+        cb: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    );
+
+    /// This overwrites the given area with bytes, which are already packed in
+    /// the [`Display::NativeFormat`] of the display, so precomputed images
+    /// can be sent as they are. The buffer holds all lines of the image, even
+    /// the ones which don't fit on the display, and can be in program memory.
+    /// By default this copies the lines in
+    /// [`Display::set_frame_memory_from_scanlines`].
+    fn set_frame_memory_from_raw(
+        &mut self,
+        buffer: &(impl ImageData + ?Sized),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let bytes_per_line = Self::NativeFormat::bytes_per_line(image_width);
+        let first_line = Self::NativeFormat::line_of(y);
+        self.set_frame_memory_from_scanlines(|line, bytes| {
+            let start = (line - first_line) * bytes_per_line;
+            // The line can be longer than the image, when the driver aligns
+            // the area to bigger units than the format.
+            for (index, byte) in bytes.iter_mut().enumerate() {
+                *byte = if index < bytes_per_line { buffer.byte(start + index) } else { 0 };
+            }
+        }, x, y, image_width, image_height)
+    }
+
     /// This just calls the [`Display::set_frame_memory_from_callback`] function, with
     /// the buffer.
     fn set_frame_memory_from_buffer(
@@ -43,19 +94,81 @@ pub trait Display<Color: Copy + Debug> {
         }, x, y, image_width, image_height)
     }
 
-    /// This just calls the [`Display::set_frame_memory_from_callback`]
-    /// function, to clear the complete display to the specified color.
+    /// This draws a packed monochrome bitmap with its top left corner at the
+    /// given position, using `color` for set bits and `bg_color` for the
+    /// others. By default this just calls the
+    /// [`Display::set_frame_memory_from_callback`] function, but drivers can
+    /// convert whole bytes to their [`Display::NativeFormat`].
+    fn set_frame_memory_from_bitmap1(
+        &mut self,
+        bitmap: &Bitmap1<impl ImageData + ?Sized>,
+        x: usize,
+        y: usize,
+        color: Color,
+        bg_color: Color,
+    ) {
+        self.set_frame_memory_from_callback(|dx, dy| {
+            if bitmap.pixel(dx - x, dy - y) {
+                color
+            } else {
+                bg_color
+            }
+        }, x, y, bitmap.width(), bitmap.height())
+    }
+
+    /// This draws a packed bitmap with four bit levels with its top left
+    /// corner at the given position. `palette` maps each level to a color of
+    /// the display. By default this just calls the
+    /// [`Display::set_frame_memory_from_callback`] function, but drivers can
+    /// convert whole bytes to their [`Display::NativeFormat`].
+    fn set_frame_memory_from_bitmap4(
+        &mut self,
+        bitmap: &Bitmap4<impl ImageData + ?Sized>,
+        x: usize,
+        y: usize,
+        palette: impl Fn(Gray4) -> Color,
+    ) {
+        self.set_frame_memory_from_callback(|dx, dy| {
+            palette(bitmap.pixel(dx - x, dy - y))
+        }, x, y, bitmap.width(), bitmap.height())
+    }
+
+    /// This fills the given area with a single color. By default this just
+    /// calls the [`Display::set_frame_memory_from_callback`] function, but
+    /// drivers can do this a lot faster.
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        self.set_frame_memory_from_callback(|_, _| color, x, y, width, height)
+    }
+
+    /// This just calls the [`Display::fill_rect`] function, to clear the
+    /// complete display to the specified color.
     fn clear_frame_memory(
         &mut self,
         clear_color: Color
     ) {
-        self.set_frame_memory_from_callback(|_, _| clear_color, 0, 0, Self::WIDTH, Self::HEIGHT)
+        self.fill_rect(0, 0, Self::WIDTH, Self::HEIGHT, clear_color)
     }
 
     /// Depending on the display, this is needed to make the updated buffer
     /// actually visible. In other cases this is just a noop.
     fn display_frame(&mut self) {}
 
+    /// This overwrites the given area just like
+    /// [`Display::set_frame_memory_from_callback`] and makes it visible right
+    /// away. Displays, which need [`Display::display_frame`], can do this
+    /// faster by only updating the given area.
+    fn update_region(
+        &mut self,
+        cb: impl Fn(usize, usize) -> Color,
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        self.set_frame_memory_from_callback(cb, x, y, image_width, image_height);
+        self.display_frame();
+    }
+
     /// The width of the display in pixel
     fn width(&self) -> usize { Self::WIDTH }
     /// The height of the display in pixel
@@ -66,8 +179,38 @@ pub trait Display<Color: Copy + Debug> {
     fn dark_color(&self) -> Color { Self::DARK_COLOR }
 }
 
+/// The direction the content moves in during a hardware scroll.
+#[derive(Clone, Copy, Debug)]
+pub enum ScrollDirection {
+    Left,
+    Right,
+}
+
+/// The time between two scroll steps, measured in frames. These are the
+/// intervals that both OLED controllers support.
+#[derive(Clone, Copy, Debug)]
+pub enum ScrollInterval {
+    Frames2,
+    Frames3,
+    Frames4,
+    Frames5,
+    Frames64,
+    Frames128,
+}
+
 use core::fmt::Debug;
 
-pub use display_epaper::EpaperDisplay;
-pub use display_oled::OledDisplay;
-pub use display_oled_wide::WideOledDisplay;
+pub use bitmap::{BitOrder, Bitmap1, Bitmap4, ImageData};
+pub use color::{Gray2, Gray4, TriColor};
+pub use display_epaper::{
+    Awake, BorderWaveform, EpaperDisplay, EpaperPanel, Epd1in54, Epd2in13, Epd2in9, Epd4in2,
+    InitSequence, Lut, RefreshMode, Sleeping, Temperature, Voltages, LUT_SIZE,
+};
+pub use display_epaper_grayscale::GrayscaleEpaperDisplay;
+pub use display_epaper_tricolor::TriColorEpaperDisplay;
+pub use display_oled::{GrayScaleTable, OledDisplay, PulseWidths};
+pub use display_oled_wide::{
+    FadeInterval, FadeMode, Size128x32, Size128x64, Size64x48, Size72x40, Size96x16,
+    WideOledDisplay, WideOledSize,
+};
+pub use format::{HorizontalMsbFirst1Bpp, NativeFormat, Packed2Bpp, Packed4Bpp, VerticalPage1Bpp};
@@ -0,0 +1,213 @@
+use avr_progmem::wrapper::ProgMem;
+
+use super::Gray4;
+
+/// The bytes of an image, which are read one at a time. This way images can
+/// be streamed from program memory, without copying them into the RAM first.
+pub trait ImageData {
+    /// The number of bytes
+    fn len(&self) -> usize;
+    /// Reads the byte at the given index.
+    fn byte(&self, index: usize) -> u8;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ImageData for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn byte(&self, index: usize) -> u8 {
+        self[index]
+    }
+}
+
+impl<const N: usize> ImageData for [u8; N] {
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn byte(&self, index: usize) -> u8 {
+        self[index]
+    }
+}
+
+/// Images in program memory, which are declared with
+/// [`avr_progmem::progmem`].
+impl<const N: usize> ImageData for ProgMem<[u8; N]> {
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn byte(&self, index: usize) -> u8 {
+        self.load_at(index)
+    }
+}
+
+/// The order of the pixels in the bytes of a [`Bitmap1`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The leftmost pixel is in the most significant bit.
+    MsbFirst,
+    /// The leftmost pixel is in the least significant bit, like in the font.
+    LsbFirst,
+}
+
+/// A monochrome image with one bit per pixel, stored row by row. Every row
+/// starts `stride` bytes after the previous one. The data can be in the RAM or
+/// in program memory.
+pub struct Bitmap1<'a, Data: ImageData + ?Sized = [u8]> {
+    data: &'a Data,
+    width: usize,
+    height: usize,
+    stride: usize,
+    bit_order: BitOrder,
+}
+
+impl<'a, Data: ImageData + ?Sized> Clone for Bitmap1<'a, Data> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, Data: ImageData + ?Sized> Copy for Bitmap1<'a, Data> {}
+
+#[allow(dead_code)]
+impl<'a, Data: ImageData + ?Sized> Bitmap1<'a, Data> {
+    /// Creates a bitmap with rows, which are packed without gaps, and the
+    /// leftmost pixel in the most significant bit. Returns `None` if the data
+    /// is too short for the size.
+    pub fn new(data: &'a Data, width: usize, height: usize) -> Option<Self> {
+        Self::with_layout(data, width, height, (width + 7) / 8, BitOrder::MsbFirst)
+    }
+
+    /// Creates a bitmap with the given distance between rows in bytes and the
+    /// given bit order. Returns `None` if the stride is too short for the
+    /// width or the data too short for the size.
+    pub fn with_layout(
+        data: &'a Data,
+        width: usize,
+        height: usize,
+        stride: usize,
+        bit_order: BitOrder,
+    ) -> Option<Self> {
+        if stride * 8 < width || data.len() < stride * height {
+            return None;
+        }
+        Some(Bitmap1 {
+            data,
+            width,
+            height,
+            stride,
+            bit_order,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// The bytes of a row, without the gap up to the next row.
+    pub fn row(&self, y: usize) -> impl Iterator<Item = u8> + 'a {
+        let data = self.data;
+        let start = y * self.stride;
+        (start..start + (self.width + 7) / 8).map(move |index| data.byte(index))
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let byte = self.data.byte(y * self.stride + x / 8);
+        let bit = match self.bit_order {
+            BitOrder::MsbFirst => 7 - x % 8,
+            BitOrder::LsbFirst => x % 8,
+        };
+        byte & (1 << bit) != 0
+    }
+}
+
+/// An image with the four bit levels of a [`Gray4`] per pixel, stored row by
+/// row. The left pixel of a byte is in the high nibble and every row starts
+/// `stride` bytes after the previous one. The data can be in the RAM or in
+/// program memory.
+pub struct Bitmap4<'a, Data: ImageData + ?Sized = [u8]> {
+    data: &'a Data,
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl<'a, Data: ImageData + ?Sized> Clone for Bitmap4<'a, Data> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, Data: ImageData + ?Sized> Copy for Bitmap4<'a, Data> {}
+
+#[allow(dead_code)]
+impl<'a, Data: ImageData + ?Sized> Bitmap4<'a, Data> {
+    /// Creates a bitmap with rows, which are packed without gaps. Returns
+    /// `None` if the data is too short for the size.
+    pub fn new(data: &'a Data, width: usize, height: usize) -> Option<Self> {
+        Self::with_stride(data, width, height, (width + 1) / 2)
+    }
+
+    /// Creates a bitmap with the given distance between rows in bytes.
+    /// Returns `None` if the stride is too short for the width or the data
+    /// too short for the size.
+    pub fn with_stride(
+        data: &'a Data,
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Option<Self> {
+        if stride * 2 < width || data.len() < stride * height {
+            return None;
+        }
+        Some(Bitmap4 {
+            data,
+            width,
+            height,
+            stride,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The bytes of a row, without the gap up to the next row.
+    pub fn row(&self, y: usize) -> impl Iterator<Item = u8> + 'a {
+        let data = self.data;
+        let start = y * self.stride;
+        (start..start + (self.width + 1) / 2).map(move |index| data.byte(index))
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Gray4 {
+        let byte = self.data.byte(y * self.stride + x / 2);
+        let level = if x % 2 == 0 { byte >> 4 } else { byte & 0x0F };
+        Gray4::new(level).unwrap()
+    }
+}
@@ -0,0 +1,72 @@
+/// The colors of a black, white and red e-paper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriColor {
+    White,
+    Black,
+    Red,
+}
+
+/// One of the four gray levels of an e-paper in grayscale mode, from black at
+/// level 0 to white at level 3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gray2(u8);
+
+#[allow(dead_code)]
+impl Gray2 {
+    pub const BLACK: Gray2 = Gray2(0);
+    pub const DARK_GRAY: Gray2 = Gray2(1);
+    pub const LIGHT_GRAY: Gray2 = Gray2(2);
+    pub const WHITE: Gray2 = Gray2(3);
+
+    /// Returns `None` if the level does not fit into 2 bits.
+    pub fn new(level: u8) -> Option<Gray2> {
+        if level <= 3 {
+            Some(Gray2(level))
+        } else {
+            None
+        }
+    }
+
+    pub fn level(self) -> u8 {
+        self.0
+    }
+}
+
+/// One of the 16 gray levels of the [`super::OledDisplay`], from black at
+/// level 0 to white at level 15.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gray4(u8);
+
+#[allow(dead_code)]
+impl Gray4 {
+    pub const BLACK: Gray4 = Gray4(0);
+    pub const WHITE: Gray4 = Gray4(15);
+
+    /// Returns `None` if the level does not fit into 4 bits.
+    pub fn new(level: u8) -> Option<Gray4> {
+        if level <= 15 {
+            Some(Gray4(level))
+        } else {
+            None
+        }
+    }
+
+    /// Converts an 8 bit luminance by dropping the lower 4 bits.
+    pub fn from_luminance(luminance: u8) -> Gray4 {
+        Gray4(luminance >> 4)
+    }
+
+    pub fn level(self) -> u8 {
+        self.0
+    }
+}
+
+impl From<bool> for Gray4 {
+    fn from(value: bool) -> Self {
+        if value {
+            Gray4::WHITE
+        } else {
+            Gray4::BLACK
+        }
+    }
+}
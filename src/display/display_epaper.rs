@@ -1,7 +1,15 @@
+use core::{iter, marker::PhantomData};
+
 use arduino_hal::{port, prelude::*, spi::{ChipSelectPin, self}};
 use embedded_hal::digital::v2::{InputPin, OutputPin};
 
-use super::Display;
+use super::{
+    BitOrder, Bitmap1, Display, HorizontalMsbFirst1Bpp, ImageData, NativeFormat,
+};
+
+/// The number of bytes in the longest line of all panels, the 400 pixels of
+/// the [`Epd4in2`].
+const LINE_BUFFER_SIZE: usize = 50;
 
 const EPAPER_SPI_SETTINGS: spi::Settings = spi::Settings {
     data_order: spi::DataOrder::MostSignificantFirst,
@@ -9,6 +17,137 @@ const EPAPER_SPI_SETTINGS: spi::Settings = spi::Settings {
     mode: embedded_hal::spi::MODE_0,
 };
 
+/// The state of an [`EpaperDisplay`], which accepts commands and can be drawn
+/// to.
+pub struct Awake;
+
+/// The state of an [`EpaperDisplay`] in deep sleep. The controller ignores
+/// everything but a hardware reset, so the only thing you can do with it is
+/// [`EpaperDisplay::wake`].
+pub struct Sleeping;
+
+/// How [`Display::display_frame`] updates the e-paper.
+#[derive(Clone, Copy, Debug)]
+pub enum RefreshMode {
+    /// The whole display flashes black and white a few times, which takes
+    /// seconds, but leaves no ghosting behind.
+    Full,
+    /// Only the pixels that changed are updated, which is a lot faster and
+    /// does not flash. This leaves a bit of ghosting behind, so every
+    /// `full_refresh_every` partial refreshes a full refresh is done instead.
+    /// With `0` this never happens automatically.
+    Partial { full_refresh_every: u8 },
+}
+
+/// The number of bytes of a waveform LUT, as written with command 0x32.
+pub const LUT_SIZE: usize = 153;
+
+/// The waveform used to drive the e-paper.
+#[derive(Clone, Copy, Debug)]
+pub enum Lut {
+    /// The waveforms stored in the OTP of the controller. The right one for
+    /// the current temperature and refresh mode is loaded on every refresh.
+    Otp,
+    /// A custom waveform, which is used for every refresh regardless of the
+    /// temperature and refresh mode.
+    Custom(&'static [u8; LUT_SIZE]),
+}
+
+/// Where the temperature comes from, which is used to select the waveform from
+/// the OTP.
+#[derive(Clone, Copy, Debug)]
+pub enum Temperature {
+    /// The built in temperature sensor is read on every refresh.
+    InternalSensor,
+    /// A temperature measured somewhere else in degrees Celsius.
+    Celsius(i8),
+}
+
+/// What the border around the active area of the panel shows.
+#[derive(Clone, Copy, Debug)]
+pub enum BorderWaveform {
+    /// The border follows the waveform of pixels which turn white.
+    White,
+    /// The border follows the waveform of pixels which turn black.
+    Black,
+    /// The border is held at VCOM, so it keeps its color during refreshes.
+    Vcom,
+    /// The border is not driven at all and keeps its last color.
+    Floating,
+}
+
+/// Voltages used instead of the defaults. The register values are described in
+/// the datasheet. The waveforms in the OTP come with their own voltages, which
+/// are loaded on every refresh, so these only stay in effect with a custom
+/// [`Lut`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Voltages {
+    /// VCOM, command 0x2C. For example 0x50 is -2.0V.
+    pub vcom: Option<u8>,
+    /// The gate voltage VGH, command 0x03.
+    pub gate: Option<u8>,
+    /// The source voltages VSH1, VSH2 and VSL, command 0x04.
+    pub source: Option<[u8; 3]>,
+}
+
+/// The command sequence used to initialize the controller after a hardware
+/// reset.
+#[derive(Clone, Copy, Debug)]
+pub enum InitSequence {
+    /// The sequence of the C library the driver is based on. It skips the
+    /// software reset and sets some things up differently.
+    Library,
+    /// The sequence from the datasheet, starting with a software reset.
+    Datasheet,
+}
+
+/// A panel driven by a SSD1680 or SSD1681 compatible controller, which all
+/// share the same commands.
+pub trait EpaperPanel {
+    /// The width of the panel in pixel, which is the number of sources used.
+    const WIDTH: usize;
+    /// The height of the panel in pixel.
+    const HEIGHT: usize;
+    /// The number of gates driven by the controller.
+    const GATES: usize = Self::HEIGHT;
+    /// The first column of the RAM in bytes, which is connected to the panel.
+    const RAM_X_OFFSET: usize = 0;
+    /// The waveform the panel is initialized with.
+    const LUT: Lut = Lut::Otp;
+}
+
+/// The 1.54" panel with 200x200 pixels.
+pub struct Epd1in54;
+
+impl EpaperPanel for Epd1in54 {
+    const WIDTH: usize = 200;
+    const HEIGHT: usize = 200;
+}
+
+/// The 2.13" panel with 122x250 pixels.
+pub struct Epd2in13;
+
+impl EpaperPanel for Epd2in13 {
+    const WIDTH: usize = 122;
+    const HEIGHT: usize = 250;
+}
+
+/// The 2.9" panel with 128x296 pixels.
+pub struct Epd2in9;
+
+impl EpaperPanel for Epd2in9 {
+    const WIDTH: usize = 128;
+    const HEIGHT: usize = 296;
+}
+
+/// The 4.2" panel with 400x300 pixels.
+pub struct Epd4in2;
+
+impl EpaperPanel for Epd4in2 {
+    const WIDTH: usize = 400;
+    const HEIGHT: usize = 300;
+}
+
 /// ```plain
 /// Pin 1 = Power 5V
 /// Pin 2 = GND
@@ -19,21 +158,62 @@ const EPAPER_SPI_SETTINGS: spi::Settings = spi::Settings {
 /// Pin 7 = Reset
 /// Pin 8 = Busy
 /// ```
-pub struct EpaperDisplay<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps> {
+pub struct EpaperDisplay<
+    DCPin: OutputPin,
+    BPin: InputPin,
+    CSPin: port::PinOps,
+    RPin: OutputPin,
+    Panel: EpaperPanel = Epd1in54,
+    State = Awake,
+> {
     data_command_pin: DCPin,
     busy_pin: BPin,
     chip_select_pin: ChipSelectPin<CSPin>,
+    reset_pin: RPin,
     spi: arduino_hal::Spi,
+    inverted: bool,
+    refresh_mode: RefreshMode,
+    partial_refreshes: u8,
+    pub(super) lut: Lut,
+    temperature: Temperature,
+    init_sequence: InitSequence,
+    border_waveform: BorderWaveform,
+    voltages: Voltages,
+    panel: PhantomData<Panel>,
+    state: PhantomData<State>,
 }
 
 #[allow(dead_code)]
-impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps> EpaperDisplay<DCPin, BPin, CSPin> {
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake>
+{
     pub fn new(
+        spi: arduino_hal::Spi,
+        chip_select_pin: ChipSelectPin<CSPin>,
+        reset_pin: RPin,
+        data_command_pin: DCPin,
+        busy_pin: BPin,
+        panel: Panel,
+    ) -> Self {
+        Self::new_with_init_sequence(
+            spi,
+            chip_select_pin,
+            reset_pin,
+            data_command_pin,
+            busy_pin,
+            panel,
+            InitSequence::Library,
+        )
+    }
+
+    pub fn new_with_init_sequence(
         mut spi: arduino_hal::Spi,
         mut chip_select_pin: ChipSelectPin<CSPin>,
-        mut reset_pin: impl OutputPin,
+        reset_pin: RPin,
         data_command_pin: DCPin,
         busy_pin: BPin,
+        _panel: Panel,
+        init_sequence: InitSequence,
     ) -> Self {
         spi.reconfigure(EPAPER_SPI_SETTINGS).unwrap();
 
@@ -41,54 +221,506 @@ impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps> EpaperDisplay<DCPin,
 
         chip_select_pin.set_high().unwrap();
 
-        let _ = reset_pin.set_low();
-        arduino_hal::delay_ms(200);
-        let _ = reset_pin.set_high();
-        arduino_hal::delay_ms(200);
-
-        // TODO: Software reset. The documentation says, this should be done,
-        // but the c library does not do it..
-
         let mut result = EpaperDisplay {
             data_command_pin,
             spi,
             busy_pin,
             chip_select_pin,
+            reset_pin,
+            inverted: false,
+            refresh_mode: RefreshMode::Full,
+            partial_refreshes: 0,
+            lut: Panel::LUT,
+            temperature: Temperature::InternalSensor,
+            init_sequence,
+            border_waveform: BorderWaveform::White,
+            voltages: Voltages::default(),
+            panel: PhantomData,
+            state: PhantomData,
+        };
+
+        result.reset();
+
+        result
+    }
+
+    /// Resets the controller and initializes it again. The frame memory is
+    /// lost, but all settings made through the driver are kept.
+    pub fn reset(&mut self) {
+        self.hardware_reset();
+        self.init();
+    }
+
+    /// Puts the display into deep sleep. The image stays visible.
+    pub fn sleep(mut self) -> EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Sleeping> {
+        self.send_command(0x10);
+        // Deep sleep mode 1, which keeps the RAM content.
+        self.send_data(&[0x01]);
+        self.into_state()
+    }
+
+    /// Inverts the colors of the whole display, without touching the frame
+    /// memory. Like every other change this is only visible after the next
+    /// [`Display::display_frame`].
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+        self.send_display_update_control_1();
+    }
+
+    /// Sets how the following calls to [`Display::display_frame`] update the
+    /// display. While the mode is [`RefreshMode::Full`] all writes also go to
+    /// the RAM holding the previous image, which is what partial refreshes
+    /// compare against. So a full refresh should be done before switching to
+    /// partial refreshes.
+    pub fn set_refresh_mode(&mut self, refresh_mode: RefreshMode) {
+        self.refresh_mode = refresh_mode;
+        self.partial_refreshes = 0;
+    }
+
+    /// Sets the waveform used for the following refreshes. A custom waveform
+    /// is uploaded right away and again after every reset.
+    pub fn set_lut(&mut self, lut: Lut) {
+        self.lut = lut;
+        if let Lut::Custom(lut) = lut {
+            self.send_lut(lut);
+        }
+    }
+
+    /// Sets the temperature used to select the waveform from the OTP. This
+    /// has no effect while a custom waveform is used.
+    pub fn set_temperature(&mut self, temperature: Temperature) {
+        self.temperature = temperature;
+        self.send_temperature();
+    }
+
+    /// Sets what the border shows after the next refresh.
+    pub fn set_border_waveform(&mut self, border_waveform: BorderWaveform) {
+        self.border_waveform = border_waveform;
+        self.send_border_waveform();
+    }
+
+    /// Sets the voltages used to drive the panel, see [`Voltages`].
+    pub fn set_voltages(&mut self, voltages: Voltages) {
+        self.voltages = voltages;
+        self.send_voltages();
+    }
+
+    pub(super) fn refresh(&mut self, partial: bool) {
+        // Enable the clock and the analog, display and disable both again.
+        let mut display_update_control_2 = 0xC7;
+        if partial {
+            // Display mode 2 has the waveform for partial refreshes.
+            display_update_control_2 |= 0x08;
+        }
+        if let Lut::Otp = self.lut {
+            // The waveform depends on the display mode and the temperature, so
+            // it is loaded again before each refresh.
+            display_update_control_2 |= 0x10;
+            if let Temperature::InternalSensor = self.temperature {
+                display_update_control_2 |= 0x20;
+            }
+        }
+
+        self.send_command(0x22);
+        self.send_data(&[display_update_control_2]);
+        self.send_command(0x20);
+        self.block_until_idle();
+    }
+
+    /// Refreshes the display, partially if asked to. Every
+    /// `full_refresh_every` partial refreshes a full refresh is done instead.
+    fn refresh_counted(&mut self, partial: bool) {
+        let partial = partial
+            && match self.refresh_mode {
+                RefreshMode::Full => true,
+                RefreshMode::Partial { full_refresh_every } => {
+                    full_refresh_every == 0 || self.partial_refreshes < full_refresh_every
+                }
+            };
+        if partial {
+            self.partial_refreshes = self.partial_refreshes.saturating_add(1);
+        } else {
+            self.partial_refreshes = 0;
+        }
+        self.refresh(partial);
+    }
+
+    /// Clips the given area to the display. Returns the first and last pixel
+    /// of the area, or `None` if nothing of it is on the display. The RAM is
+    /// written in whole bytes, so the written area starts at `x & 0xF8` and
+    /// ends at `x_end | 0x07`.
+    pub(super) fn clip(
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) -> Option<(usize, usize, usize, usize)> {
+        if x >= Panel::WIDTH || y >= Panel::HEIGHT || image_width == 0 || image_height == 0 {
+            return None;
+        }
+
+        let x_end = if x + image_width >= Panel::WIDTH {
+            Panel::WIDTH - 1
+        } else {
+            x + image_width - 1
         };
+        let y_end = if y + image_height >= Panel::HEIGHT {
+            Panel::HEIGHT - 1
+        } else {
+            y + image_height - 1
+        };
+        Some((x, y, x_end, y_end))
+    }
+
+    /// Writes the given area of a RAM line by line. `f` fills a line with one
+    /// bit per pixel, the leftmost pixel in the most significant bit.
+    pub(super) fn write_ram_from_scanlines(
+        &mut self,
+        ram: u8,
+        f: &mut impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        x_end: usize,
+        y_end: usize,
+    ) {
+        let bytes_per_line = (x_end >> 3) - (x >> 3) + 1;
+
+        // The address counter wraps to the start of the next line at the end
+        // of the window, so all lines go out in a single burst.
+        self.set_memory_area(x, y, x_end, y_end);
+        self.set_memory_pointer(x, y);
+        self.send_command(ram);
+        self.send_data_from((y..=y_end).flat_map(|line| {
+            let mut buffer = [0; LINE_BUFFER_SIZE];
+            f(line, &mut buffer[..bytes_per_line]);
+            buffer.into_iter().take(bytes_per_line)
+        }));
+    }
+
+    /// Writes one bit of lines with two bits per pixel into a RAM. This is
+    /// used by the displays, which need both RAMs for the color of a pixel.
+    /// The area is the one returned by [`EpaperDisplay::clip`].
+    pub(super) fn write_ram_from_double_scanlines(
+        &mut self,
+        ram: u8,
+        bit: u8,
+        f: &mut impl FnMut(usize, &mut [u8]),
+        area: (usize, usize, usize, usize),
+    ) {
+        let (x, y, x_end, y_end) = area;
+
+        self.write_ram_from_scanlines(
+            ram,
+            &mut |line, buffer: &mut [u8]| {
+                let mut double_buffer = [0; 2 * LINE_BUFFER_SIZE];
+                let double_buffer = &mut double_buffer[..2 * buffer.len()];
+                f(line, double_buffer);
+                for (byte, pair) in buffer.iter_mut().zip(double_buffer.chunks(2)) {
+                    *byte = 0;
+                    for pixel in 0..8 {
+                        let shift = 6 - 2 * (pixel % 4);
+                        *byte <<= 1;
+                        *byte |= (pair[pixel / 4] >> shift >> bit) & 1;
+                    }
+                }
+            },
+            x,
+            y,
+            x_end,
+            y_end,
+        );
+    }
+
+    pub(super) fn write_ram_from_callback(
+        &mut self,
+        ram: u8,
+        f: &impl Fn(usize, usize) -> bool,
+        x: usize,
+        y: usize,
+        x_end: usize,
+        y_end: usize,
+    ) {
+        self.write_ram_from_scanlines(
+            ram,
+            &mut |line, buffer: &mut [u8]| {
+                for (cursor, byte) in (x & 0xF8..=x_end).step_by(8).zip(buffer.iter_mut()) {
+                    *byte = 0;
+                    for pixel_x in cursor..cursor + 8 {
+                        *byte <<= 1;
+                        // The first and last byte can reach past the area,
+                        // for example on the 2.13" panel, which is not a
+                        // multiple of 8 wide.
+                        if (x..=x_end).contains(&pixel_x) {
+                            *byte |= f(pixel_x, line) as u8;
+                        }
+                    }
+                }
+            },
+            x,
+            y,
+            x_end,
+            y_end,
+        );
+    }
+
+    /// Fills the given area of a RAM with a single value.
+    pub(super) fn fill_ram(
+        &mut self,
+        ram: u8,
+        value: bool,
+        x: usize,
+        y: usize,
+        x_end: usize,
+        y_end: usize,
+    ) {
+        let whole_panel =
+            x == 0 && y == 0 && x_end == Panel::WIDTH - 1 && y_end == Panel::HEIGHT - 1;
+        // The controller can fill the whole RAM with a pattern by itself. The
+        // biggest step is 200 pixels in both directions, so on panels up to
+        // that size the pattern is a single color.
+        if whole_panel && Panel::WIDTH <= 200 && Panel::HEIGHT <= 200 {
+            let auto_write = match ram {
+                0x24 => 0x47,
+                _ => 0x46,
+            };
+            // The pattern fills the current window from the address counter,
+            // which can still be set up for an earlier write.
+            self.set_memory_area(x, y, x_end, y_end);
+            self.set_memory_pointer(x, y);
+            self.send_command(auto_write);
+            self.send_data(&[(value as u8) << 7 | 0x55]);
+            self.block_until_idle();
+            return;
+        }
+
+        self.set_memory_area(x, y, x_end, y_end);
+        self.set_memory_pointer(x, y);
+        self.send_command(ram);
+        let byte = if value { 0xFF } else { 0x00 };
+        let bytes_per_line = (x_end >> 3) - (x >> 3) + 1;
+        self.send_data_from(iter::repeat(byte).take(bytes_per_line * (y_end - y + 1)));
+    }
+
+    /// Writes the given area of a RAM from packed lines, which are
+    /// `bytes_per_line` apart in the buffer. Lines, which don't start on a
+    /// byte of the RAM, are shifted into place. The area is the one returned
+    /// by [`EpaperDisplay::clip`].
+    fn write_ram_from_raw(
+        &mut self,
+        ram: u8,
+        buffer: &(impl ImageData + ?Sized),
+        bytes_per_line: usize,
+        area: (usize, usize, usize, usize),
+    ) {
+        let (x, y, x_end, y_end) = area;
+        let bytes = (x_end >> 3) - (x >> 3) + 1;
+        let shift = x & 0x07;
+        // The pixels after the area in the last byte are cleared, just like
+        // the callback does.
+        let last_byte_mask = !(0x7F >> (x_end & 0x07));
+
+        self.set_memory_area(x, y, x_end, y_end);
+        self.set_memory_pointer(x, y);
+        self.send_command(ram);
+        self.send_data_from((0..=y_end - y).flat_map(|line| {
+            let start = line * bytes_per_line;
+            let byte = move |index: usize| {
+                if index < bytes_per_line {
+                    buffer.byte(start + index) as u16
+                } else {
+                    0
+                }
+            };
+            (0..bytes).map(move |index| {
+                let previous = if index == 0 { 0 } else { byte(index - 1) };
+                let shifted = ((previous << 8 | byte(index)) >> shift) as u8;
+                if index == bytes - 1 {
+                    shifted & last_byte_mask
+                } else {
+                    shifted
+                }
+            })
+        }));
+    }
+}
+
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Sleeping>
+{
+    /// Wakes the display up from deep sleep. This needs a hardware reset,
+    /// which also loses all configuration, so the display is initialized
+    /// again afterwards.
+    pub fn wake(self) -> EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake> {
+        let mut result: EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake> =
+            self.into_state();
+        result.reset();
+        result
+    }
+}
 
-        result.send_command(0x01);
-        result.send_data(&mut [0xC7, 0, 0]);
+#[allow(dead_code)]
+impl<
+    DCPin: OutputPin,
+    BPin: InputPin,
+    CSPin: port::PinOps,
+    RPin: OutputPin,
+    Panel: EpaperPanel,
+    State,
+>
+    EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, State>
+{
+    fn into_state<NewState>(
+        self,
+    ) -> EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, NewState> {
+        EpaperDisplay {
+            data_command_pin: self.data_command_pin,
+            busy_pin: self.busy_pin,
+            chip_select_pin: self.chip_select_pin,
+            reset_pin: self.reset_pin,
+            spi: self.spi,
+            inverted: self.inverted,
+            refresh_mode: self.refresh_mode,
+            partial_refreshes: self.partial_refreshes,
+            lut: self.lut,
+            temperature: self.temperature,
+            init_sequence: self.init_sequence,
+            border_waveform: self.border_waveform,
+            voltages: self.voltages,
+            panel: PhantomData,
+            state: PhantomData,
+        }
+    }
 
-        result.send_command(0x11);
-        result.send_data(&mut [0x03]);
+    fn hardware_reset(&mut self) {
+        let _ = self.reset_pin.set_low();
+        arduino_hal::delay_ms(200);
+        let _ = self.reset_pin.set_high();
+        arduino_hal::delay_ms(200);
+    }
 
-        result.send_command(0x44);
-        result.send_data(&mut [0x00, 0x18]);
+    fn init(&mut self) {
+        // The documentation says, a software reset should be done here, but
+        // the c library does not do it.
+        if let InitSequence::Datasheet = self.init_sequence {
+            self.send_command(0x12);
+            self.block_until_idle();
+        }
 
-        result.send_command(0x45);
-        result.send_data(&mut [0xC7, 0, 0, 0]);
+        let last_gate = Panel::GATES - 1;
+        let last_column = Panel::RAM_X_OFFSET + ((Panel::WIDTH - 1) >> 3);
 
-        result.send_command(0x3C);
-        result.send_data(&mut [0x01]);
+        self.send_command(0x01);
+        self.send_data(&[last_gate as u8, (last_gate >> 8) as u8, 0]);
+
+        self.send_command(0x11);
+        self.send_data(&[0x03]);
+
+        self.send_command(0x44);
+        self.send_data(&[Panel::RAM_X_OFFSET as u8, last_column as u8]);
+
+        self.send_command(0x45);
+        match self.init_sequence {
+            // The library sets the window from the last to the first gate,
+            // even though the data entry mode counts upwards.
+            InitSequence::Library => {
+                self.send_data(&[last_gate as u8, (last_gate >> 8) as u8, 0, 0])
+            }
+            InitSequence::Datasheet => {
+                self.send_data(&[0, 0, last_gate as u8, (last_gate >> 8) as u8])
+            }
+        }
+
+        self.send_border_waveform();
 
         // Until here the documentation and the library are very similiar. But
         // now they diverge. We'll try to stay close to the library at first and
         // test out other things later.
 
-        result.send_command(0x21);
-        result.send_data(&mut [0x00]);
+        self.send_display_update_control_1();
 
-        result.send_command(0x18);
-        result.send_data(&mut [0x80]);
+        self.send_temperature();
 
-        result.send_command(0x22);
-        result.send_data(&mut [0xB1]);
+        match self.lut {
+            Lut::Otp => {
+                self.send_command(0x22);
+                if let Temperature::InternalSensor = self.temperature {
+                    self.send_data(&[0xB1]);
+                } else {
+                    // Load the waveform without reading the sensor, which
+                    // would overwrite the written temperature.
+                    self.send_data(&[0x91]);
+                }
 
-        result.send_command(0x20);
+                self.send_command(0x20);
 
-        result.block_until_idle();
+                self.block_until_idle();
+            }
+            Lut::Custom(lut) => self.send_lut(lut),
+        }
 
-        result
+        self.send_voltages();
+
+        if let InitSequence::Datasheet = self.init_sequence {
+            self.set_memory_pointer(0, 0);
+        }
+    }
+
+    fn send_temperature(&mut self) {
+        match self.temperature {
+            Temperature::InternalSensor => {
+                self.send_command(0x18);
+                self.send_data(&[0x80]);
+            }
+            Temperature::Celsius(celsius) => {
+                // The register holds 1/16 degrees in the upper 12 bits.
+                self.send_command(0x1A);
+                self.send_data(&[celsius as u8, 0x00]);
+            }
+        }
+    }
+
+    fn send_lut(&mut self, lut: &[u8; LUT_SIZE]) {
+        self.send_command(0x32);
+        self.send_data(lut);
+    }
+
+    fn send_border_waveform(&mut self) {
+        let border_waveform = match self.border_waveform {
+            // Follow the LUT with the transitions LUT1 and LUT0, which end
+            // white and black. The library sends 0x01 for the same transition,
+            // which is kept for its init sequence.
+            BorderWaveform::White => match self.init_sequence {
+                InitSequence::Library => 0x01,
+                InitSequence::Datasheet => 0x05,
+            },
+            BorderWaveform::Black => 0x04,
+            BorderWaveform::Vcom => 0x80,
+            // HiZ
+            BorderWaveform::Floating => 0xC0,
+        };
+        self.send_command(0x3C);
+        self.send_data(&[border_waveform]);
+    }
+
+    fn send_voltages(&mut self) {
+        if let Some(vcom) = self.voltages.vcom {
+            self.send_command(0x2C);
+            self.send_data(&[vcom]);
+        }
+        if let Some(gate) = self.voltages.gate {
+            self.send_command(0x03);
+            self.send_data(&[gate]);
+        }
+        if let Some(source) = self.voltages.source {
+            self.send_command(0x04);
+            self.send_data(&source);
+        }
+    }
+
+    fn send_display_update_control_1(&mut self) {
+        self.send_command(0x21);
+        // Either shows the black and white RAM as is or inverted.
+        self.send_data(&[if self.inverted { 0x08 } else { 0x00 }]);
     }
 
     fn send_command(&mut self, command: u8) {
@@ -96,17 +728,26 @@ impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps> EpaperDisplay<DCPin,
             .set_low()
             .unwrap_or_else(|_| todo!("Implement good panic"));
         self.chip_select_pin.set_low().unwrap();
-        self.spi.transfer(&mut [command]).unwrap();
+        self.spi.write(&[command]).unwrap();
         self.chip_select_pin.set_high().unwrap();
     }
 
-    fn send_data(&mut self, data: &mut [u8]) {
+    fn send_data(&mut self, data: &[u8]) {
         self.data_command_pin
             .set_high()
             .unwrap_or_else(|_| todo!("Implement good panic"));
         self.chip_select_pin.set_low().unwrap();
-        for word in data {
-            self.spi.transfer(&mut [*word]).unwrap();
+        self.spi.write(data).unwrap();
+        self.chip_select_pin.set_high().unwrap();
+    }
+
+    /// Sends the bytes as data as they are produced, keeping chip select low
+    /// for the whole burst.
+    fn send_data_from(&mut self, data: impl IntoIterator<Item = u8>) {
+        let _ = self.data_command_pin.set_high();
+        self.chip_select_pin.set_low().unwrap();
+        for byte in data {
+            self.spi.write(&[byte]).unwrap();
         }
         self.chip_select_pin.set_high().unwrap();
     }
@@ -121,106 +762,179 @@ impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps> EpaperDisplay<DCPin,
         }
     }
 
-    fn set_frame_memory_from_raw(
-        &mut self,
-        image_buffer: &mut [u8],
-        mut x: usize,
-        y: usize,
-        mut image_width: usize,
-        image_height: usize,
-    ) {
-        x &= 0xF8;
-        image_width &= 0xF8;
-
-        let x_end = if x + image_width >= Self::WIDTH {
-            Self::WIDTH - 1
-        } else {
-            x + image_width - 1
-        };
-        let y_end = if y + image_height >= Self::HEIGHT {
-            Self::HEIGHT - 1
-        } else {
-            y + image_height - 1
-        };
-
-        self.set_memory_area(x, y, x_end, y_end);
-
-        for line in y..=y_end {
-            self.set_memory_pointer(x, line);
-            self.send_command(0x24);
-            self.send_data(
-                &mut image_buffer[(line - y) * image_width / 8..(line - y + 1) * image_width / 8],
-            );
-        }
-    }
-
     fn set_memory_area(&mut self, x: usize, y: usize, x_end: usize, y_end: usize) {
         self.send_command(0x44);
-        self.send_data(&mut [(x >> 3) as u8, (x_end >> 3) as u8]);
+        self.send_data(&[
+            (Panel::RAM_X_OFFSET + (x >> 3)) as u8,
+            (Panel::RAM_X_OFFSET + (x_end >> 3)) as u8,
+        ]);
         self.send_command(0x45);
-        self.send_data(&mut [y as u8, (y >> 8) as u8, y_end as u8, (y_end >> 8) as u8]);
+        self.send_data(&[y as u8, (y >> 8) as u8, y_end as u8, (y_end >> 8) as u8]);
     }
 
     fn set_memory_pointer(&mut self, x: usize, y: usize) {
         self.send_command(0x4E);
-        self.send_data(&mut [(x >> 3) as u8]);
+        self.send_data(&[(Panel::RAM_X_OFFSET + (x >> 3)) as u8]);
         self.send_command(0x4F);
-        self.send_data(&mut [y as u8, (y >> 8) as u8]);
-        self.block_until_idle();
+        self.send_data(&[y as u8, (y >> 8) as u8]);
     }
 }
 
-impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps> Display<bool>
-    for EpaperDisplay<DCPin, BPin, CSPin>
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    Display<bool> for EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake>
 {
     fn set_frame_memory_from_callback(
         &mut self,
         f: impl Fn(usize, usize) -> bool,
-        mut x: usize,
+        x: usize,
         y: usize,
-        mut image_width: usize,
+        image_width: usize,
         image_height: usize,
     ) {
-        x &= 0xF8;
-        image_width &= 0xF8;
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
+        };
 
-        let x_end = if x + image_width >= Self::WIDTH {
-            Self::WIDTH - 1
-        } else {
-            x + image_width - 1
+        self.write_ram_from_callback(0x24, &f, x, y, x_end, y_end);
+        // Partial refreshes compare against the image in the second RAM, so
+        // it has to hold what is visible after a full refresh. After a partial
+        // refresh the controller copies the new image into it by itself, so
+        // it is only written here in the full refresh mode.
+        if let RefreshMode::Full = self.refresh_mode {
+            self.write_ram_from_callback(0x26, &f, x, y, x_end, y_end);
+        }
+    }
+
+    /// A set bit is white.
+    fn set_frame_memory_from_scanlines(
+        &mut self,
+        mut cb: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
         };
-        let y_end = if y + image_height >= Self::HEIGHT {
-            Self::HEIGHT - 1
-        } else {
-            y + image_height - 1
+
+        self.write_ram_from_scanlines(0x24, &mut cb, x, y, x_end, y_end);
+        if let RefreshMode::Full = self.refresh_mode {
+            self.write_ram_from_scanlines(0x26, &mut cb, x, y, x_end, y_end);
+        }
+    }
+
+    /// The lines are sent straight from the buffer, without copying them.
+    fn set_frame_memory_from_raw(
+        &mut self,
+        buffer: &(impl ImageData + ?Sized),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let area = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
         };
+        let bytes_per_line = HorizontalMsbFirst1Bpp::bytes_per_line(image_width);
 
-        self.set_memory_area(x, y, x_end, y_end);
+        self.write_ram_from_raw(0x24, buffer, bytes_per_line, area);
+        if let RefreshMode::Full = self.refresh_mode {
+            self.write_ram_from_raw(0x26, buffer, bytes_per_line, area);
+        }
+    }
 
-        for line in y..=y_end {
-            self.set_memory_pointer(x, line);
-            self.send_command(0x24);
-            for cursor in (x..=x_end).step_by(8) {
-                let mut byte = 0;
-                for pixel_x in 0..8 {
-                    byte <<= 1;
-                    byte |= f(cursor + pixel_x, line) as u8;
-                }
-                self.send_data(&mut [byte]);
-            }
+    /// The bytes of the bitmap are sent as they are, only reversed or
+    /// inverted if needed. Bitmaps, which don't line up with the bytes of the
+    /// RAM, are drawn pixel by pixel.
+    fn set_frame_memory_from_bitmap1(
+        &mut self,
+        bitmap: &Bitmap1<impl ImageData + ?Sized>,
+        x: usize,
+        y: usize,
+        color: bool,
+        bg_color: bool,
+    ) {
+        if color == bg_color {
+            self.fill_rect(x, y, bitmap.width(), bitmap.height(), color);
+            return;
         }
+        if x % 8 != 0 || bitmap.width() % 8 != 0 {
+            self.set_frame_memory_from_callback(
+                |dx, dy| if bitmap.pixel(dx - x, dy - y) { color } else { bg_color },
+                x,
+                y,
+                bitmap.width(),
+                bitmap.height(),
+            );
+            return;
+        }
+
+        let bit_order = bitmap.bit_order();
+        self.set_frame_memory_from_scanlines(
+            |line, buffer| {
+                for (byte, bits) in buffer.iter_mut().zip(bitmap.row(line - y)) {
+                    let bits = match bit_order {
+                        BitOrder::MsbFirst => bits,
+                        BitOrder::LsbFirst => bits.reverse_bits(),
+                    };
+                    *byte = if color { bits } else { !bits };
+                }
+            },
+            x,
+            y,
+            bitmap.width(),
+            bitmap.height(),
+        );
     }
 
     fn display_frame(&mut self) {
-        self.send_command(0x22);
-        self.send_data(&mut [0xC7]);
-        self.send_command(0x20);
-        self.block_until_idle();
+        let partial = matches!(self.refresh_mode, RefreshMode::Partial { .. });
+        self.refresh_counted(partial);
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: bool) {
+        let (x, y, x_end, y_end) = match Self::clip(x, y, width, height) {
+            Some(area) => area,
+            None => return,
+        };
+
+        self.fill_ram(0x24, color, x, y, x_end, y_end);
+        if let RefreshMode::Full = self.refresh_mode {
+            self.fill_ram(0x26, color, x, y, x_end, y_end);
+        }
+    }
+
+    /// This only writes the area into the RAM with the new image and does a
+    /// partial refresh, whatever the [`RefreshMode`] is, so the rest of the
+    /// display doesn't flash. The refresh counts towards the next full refresh
+    /// just like the ones of [`Display::display_frame`].
+    fn update_region(
+        &mut self,
+        f: impl Fn(usize, usize) -> bool,
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
+        };
+
+        // The second RAM still holds the previous image to compare against,
+        // and the controller copies the new image into it after the refresh.
+        self.write_ram_from_callback(0x24, &f, x, y, x_end, y_end);
+        self.refresh_counted(true);
     }
 
     const PIXEL_PER_BYTE: usize = 8;
-    const HEIGHT: usize = 200;
-    const WIDTH: usize = 200;
+    type NativeFormat = HorizontalMsbFirst1Bpp;
+    const HEIGHT: usize = Panel::HEIGHT;
+    const WIDTH: usize = Panel::WIDTH;
     const DARK_COLOR: bool = false;
     const LIGHT_COLOR: bool = true;
 }
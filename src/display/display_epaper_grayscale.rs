@@ -0,0 +1,163 @@
+use arduino_hal::port;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use super::{
+    Awake, Display, EpaperDisplay, EpaperPanel, Epd1in54, Gray2, Lut, Packed2Bpp, RefreshMode,
+    LUT_SIZE,
+};
+
+/// The waveform for four gray levels. Each pixel selects one of the first four
+/// LUTs with its bits in the red and the black and white RAM. In the first
+/// pass every pixel is driven black and then white. In the second pass it is
+/// driven back towards black for longer the darker it should be.
+static GRAY2_LUT: [u8; LUT_SIZE] = [
+    // LUT0, black
+    0x60, 0x54, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // LUT1, light gray
+    0x60, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // LUT2, dark gray
+    0x60, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // LUT3, white
+    0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // LUT4, VCOM
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // Timing of the phases A, B, C and D and the repeat count of each group.
+    0x14, 0x14, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x04, 0x04, 0x00, 0x08, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // Frame rate
+    0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+    // Gate scan selection
+    0x00, 0x00, 0x00,
+];
+
+/// An [`EpaperDisplay`] showing four gray levels, by using both RAMs for the
+/// color of a pixel. This only works with full refreshes.
+pub struct GrayscaleEpaperDisplay<
+    DCPin: OutputPin,
+    BPin: InputPin,
+    CSPin: port::PinOps,
+    RPin: OutputPin,
+    Panel: EpaperPanel = Epd1in54,
+> {
+    display: EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake>,
+    previous_lut: Lut,
+}
+
+#[allow(dead_code)]
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    GrayscaleEpaperDisplay<DCPin, BPin, CSPin, RPin, Panel>
+{
+    pub fn new(mut display: EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake>) -> Self {
+        let previous_lut = display.lut;
+        display.set_refresh_mode(RefreshMode::Full);
+        display.set_lut(Lut::Custom(&GRAY2_LUT));
+        GrayscaleEpaperDisplay {
+            display,
+            previous_lut,
+        }
+    }
+
+    /// Switches back to black and white with the waveform used before.
+    pub fn into_monochrome(mut self) -> EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake> {
+        self.display.set_lut(self.previous_lut);
+        self.display
+    }
+}
+
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    Display<Gray2> for GrayscaleEpaperDisplay<DCPin, BPin, CSPin, RPin, Panel>
+{
+    fn set_frame_memory_from_callback(
+        &mut self,
+        f: impl Fn(usize, usize) -> Gray2,
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let area = EpaperDisplay::<DCPin, BPin, CSPin, RPin, Panel, Awake>::clip(
+            x,
+            y,
+            image_width,
+            image_height,
+        );
+        let (x, y, x_end, y_end) = match area {
+            Some(area) => area,
+            None => return,
+        };
+
+        self.display.write_ram_from_callback(
+            0x24,
+            &|x, y| f(x, y).level() & 0b10 != 0,
+            x,
+            y,
+            x_end,
+            y_end,
+        );
+        self.display.write_ram_from_callback(
+            0x26,
+            &|x, y| f(x, y).level() & 0b01 != 0,
+            x,
+            y,
+            x_end,
+            y_end,
+        );
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Gray2) {
+        let area =
+            EpaperDisplay::<DCPin, BPin, CSPin, RPin, Panel, Awake>::clip(x, y, width, height);
+        let (x, y, x_end, y_end) = match area {
+            Some(area) => area,
+            None => return,
+        };
+
+        self.display.fill_ram(0x24, color.level() & 0b10 != 0, x, y, x_end, y_end);
+        self.display.fill_ram(0x26, color.level() & 0b01 != 0, x, y, x_end, y_end);
+    }
+
+    /// The two bits of a pixel are the level of its [`Gray2`].
+    fn set_frame_memory_from_scanlines(
+        &mut self,
+        mut cb: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let area = EpaperDisplay::<DCPin, BPin, CSPin, RPin, Panel, Awake>::clip(
+            x,
+            y,
+            image_width,
+            image_height,
+        );
+        let area = match area {
+            Some(area) => area,
+            None => return,
+        };
+
+        self.display.write_ram_from_double_scanlines(0x24, 1, &mut cb, area);
+        self.display.write_ram_from_double_scanlines(0x26, 0, &mut cb, area);
+    }
+
+    fn display_frame(&mut self) {
+        self.display.refresh(false);
+    }
+
+    const PIXEL_PER_BYTE: usize = 4;
+    type NativeFormat = Packed2Bpp;
+    const HEIGHT: usize = Panel::HEIGHT;
+    const WIDTH: usize = Panel::WIDTH;
+    const DARK_COLOR: Gray2 = Gray2::BLACK;
+    const LIGHT_COLOR: Gray2 = Gray2::WHITE;
+}
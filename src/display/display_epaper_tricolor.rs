@@ -0,0 +1,180 @@
+use arduino_hal::{port, spi::ChipSelectPin};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use super::{
+    Awake, BorderWaveform, Display, EpaperDisplay, EpaperPanel, Epd1in54, Lut, Packed2Bpp,
+    Sleeping, Temperature, TriColor, Voltages,
+};
+
+/// The black, white and red variant of the [`EpaperDisplay`]. The controller
+/// is the same, but the second RAM holds the red pixels instead of the
+/// previous image, so there are no partial refreshes.
+pub struct TriColorEpaperDisplay<
+    DCPin: OutputPin,
+    BPin: InputPin,
+    CSPin: port::PinOps,
+    RPin: OutputPin,
+    Panel: EpaperPanel = Epd1in54,
+    State = Awake,
+> {
+    display: EpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, State>,
+}
+
+#[allow(dead_code)]
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    TriColorEpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake>
+{
+    pub fn new(
+        spi: arduino_hal::Spi,
+        chip_select_pin: ChipSelectPin<CSPin>,
+        reset_pin: RPin,
+        data_command_pin: DCPin,
+        busy_pin: BPin,
+        panel: Panel,
+    ) -> Self {
+        TriColorEpaperDisplay {
+            display: EpaperDisplay::new(
+                spi,
+                chip_select_pin,
+                reset_pin,
+                data_command_pin,
+                busy_pin,
+                panel,
+            ),
+        }
+    }
+
+    /// Puts the display into deep sleep. The image stays visible.
+    pub fn sleep(self) -> TriColorEpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Sleeping> {
+        TriColorEpaperDisplay {
+            display: self.display.sleep(),
+        }
+    }
+
+    /// See [`EpaperDisplay::reset`].
+    pub fn reset(&mut self) {
+        self.display.reset();
+    }
+
+    /// See [`EpaperDisplay::set_border_waveform`].
+    pub fn set_border_waveform(&mut self, border_waveform: BorderWaveform) {
+        self.display.set_border_waveform(border_waveform);
+    }
+
+    /// See [`EpaperDisplay::set_voltages`].
+    pub fn set_voltages(&mut self, voltages: Voltages) {
+        self.display.set_voltages(voltages);
+    }
+
+    /// See [`EpaperDisplay::set_lut`].
+    pub fn set_lut(&mut self, lut: Lut) {
+        self.display.set_lut(lut);
+    }
+
+    /// See [`EpaperDisplay::set_temperature`].
+    pub fn set_temperature(&mut self, temperature: Temperature) {
+        self.display.set_temperature(temperature);
+    }
+}
+
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    TriColorEpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Sleeping>
+{
+    /// Wakes the display up from deep sleep, see [`EpaperDisplay::wake`].
+    pub fn wake(self) -> TriColorEpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake> {
+        TriColorEpaperDisplay {
+            display: self.display.wake(),
+        }
+    }
+}
+
+impl<DCPin: OutputPin, BPin: InputPin, CSPin: port::PinOps, RPin: OutputPin, Panel: EpaperPanel>
+    Display<TriColor> for TriColorEpaperDisplay<DCPin, BPin, CSPin, RPin, Panel, Awake>
+{
+    fn set_frame_memory_from_callback(
+        &mut self,
+        f: impl Fn(usize, usize) -> TriColor,
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let area = EpaperDisplay::<DCPin, BPin, CSPin, RPin, Panel, Awake>::clip(
+            x,
+            y,
+            image_width,
+            image_height,
+        );
+        let (x, y, x_end, y_end) = match area {
+            Some(area) => area,
+            None => return,
+        };
+
+        // The black and white RAM has a 1 for white pixels. The red RAM wins
+        // over it, so red pixels can be anything in there.
+        self.display.write_ram_from_callback(
+            0x24,
+            &|x, y| f(x, y) != TriColor::Black,
+            x,
+            y,
+            x_end,
+            y_end,
+        );
+        self.display.write_ram_from_callback(
+            0x26,
+            &|x, y| f(x, y) == TriColor::Red,
+            x,
+            y,
+            x_end,
+            y_end,
+        );
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: TriColor) {
+        let area =
+            EpaperDisplay::<DCPin, BPin, CSPin, RPin, Panel, Awake>::clip(x, y, width, height);
+        let (x, y, x_end, y_end) = match area {
+            Some(area) => area,
+            None => return,
+        };
+
+        self.display.fill_ram(0x24, color != TriColor::Black, x, y, x_end, y_end);
+        self.display.fill_ram(0x26, color == TriColor::Red, x, y, x_end, y_end);
+    }
+
+    /// The high bit of a pixel is set if it isn't black and the low bit if it
+    /// is red, so `0b00` is black, `0b10` white and `0b01` red.
+    fn set_frame_memory_from_scanlines(
+        &mut self,
+        mut cb: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        let area = EpaperDisplay::<DCPin, BPin, CSPin, RPin, Panel, Awake>::clip(
+            x,
+            y,
+            image_width,
+            image_height,
+        );
+        let area = match area {
+            Some(area) => area,
+            None => return,
+        };
+
+        self.display.write_ram_from_double_scanlines(0x24, 1, &mut cb, area);
+        self.display.write_ram_from_double_scanlines(0x26, 0, &mut cb, area);
+    }
+
+    fn display_frame(&mut self) {
+        self.display.refresh(false);
+    }
+
+    const PIXEL_PER_BYTE: usize = 4;
+    type NativeFormat = Packed2Bpp;
+    const HEIGHT: usize = Panel::HEIGHT;
+    const WIDTH: usize = Panel::WIDTH;
+    const DARK_COLOR: TriColor = TriColor::Black;
+    const LIGHT_COLOR: TriColor = TriColor::White;
+}
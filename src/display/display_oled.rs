@@ -1,7 +1,15 @@
+use core::iter;
+
 use arduino_hal::{port, prelude::*, spi::{ChipSelectPin, self}};
 use embedded_hal::digital::v2::OutputPin;
 
-use super::Display;
+use super::{
+    Bitmap1, Bitmap4, Display, Gray4, ImageData, NativeFormat, Packed4Bpp, ScrollDirection,
+    ScrollInterval,
+};
+
+//A line of 128 pixels with 4 bits each
+const LINE_BUFFER_SIZE: usize = 64;
 
 const OLED_SPI_SETTINGS: spi::Settings = spi::Settings {
     data_order: spi::DataOrder::MostSignificantFirst,
@@ -14,10 +22,49 @@ const OLED_SPI_SETTINGS: spi::Settings = spi::Settings {
 // efficent, but we cannot store this buffer, since it is too big.
 // pub static mut BUFFER: [u8; DISPLAY_HEIGHT * DISPLAY_WIDTH] = [0; DISPLAY_HEIGHT * DISPLAY_WIDTH];
 
+/// Pulse widths for the gray levels 1 to 15, which follow a gamma of 2.2.
+const GAMMA_22: [u8; 15] = [1, 2, 3, 4, 6, 8, 12, 16, 20, 26, 32, 39, 46, 54, 63];
+
+/// The gray scale table maps the gray levels 1 to 15 to the time, the pixels
+/// are driven for. Level 0 is always off.
+#[derive(Clone, Copy, Debug)]
+pub enum GrayScaleTable {
+    /// The default table after a reset, where the brightness grows linearly.
+    Linear,
+    /// A table for a gamma of 2.2, so gradients look perceptually even.
+    Gamma22,
+    /// A table with custom pulse widths.
+    Custom(PulseWidths),
+}
+
+/// Pulse widths between 0 and 63 DCLKs for the gray levels 1 to 15, which
+/// increase strictly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PulseWidths([u8; 15]);
+
+#[allow(dead_code)]
+impl PulseWidths {
+    /// Returns `None` if a pulse width does not fit into 6 bits or is not
+    /// longer than the one before it.
+    pub fn new(pulse_widths: [u8; 15]) -> Option<PulseWidths> {
+        let increasing = pulse_widths.windows(2).all(|pair| pair[0] < pair[1]);
+        if increasing && pulse_widths[14] <= 63 {
+            Some(PulseWidths(pulse_widths))
+        } else {
+            None
+        }
+    }
+
+    pub fn pulse_widths(self) -> [u8; 15] {
+        self.0
+    }
+}
+
 pub struct OledDisplay<DCPin: OutputPin, CSPin: port::PinOps> {
     data_command_pin: DCPin,
     chip_select_pin: ChipSelectPin<CSPin>,
     spi: arduino_hal::Spi,
+    scrolling: bool,
 }
 
 #[allow(dead_code)]
@@ -42,9 +89,10 @@ impl<DCPin: OutputPin, CSPin: port::PinOps> OledDisplay<DCPin, CSPin> {
             data_command_pin,
             spi,
             chip_select_pin,
+            scrolling: false,
         };
 
-        result.send_command(&mut [
+        result.send_command(&[
             0xae, //Set display off
             0xa0, //Set re-map
             0x51, 
@@ -84,38 +132,135 @@ impl<DCPin: OutputPin, CSPin: port::PinOps> OledDisplay<DCPin, CSPin> {
         ]);
 
         arduino_hal::delay_ms(200);
-        result.send_command(&mut [0xaf]);
+        result.send_command(&[0xaf]);
         result
     }
 
-    fn send_command(&mut self, command_and_args: &mut [u8]) {
+    /// Turns the panel off. The controller keeps running and still accepts
+    /// writes to the frame memory, which are shown after
+    /// [`OledDisplay::wake`].
+    pub fn sleep(&mut self) {
+        self.send_command(&[0xae]);
+    }
+
+    /// Turns the panel back on after [`OledDisplay::sleep`].
+    pub fn wake(&mut self) {
+        self.send_command(&[0xaf]);
+    }
+
+    /// Inverts the gray levels of the whole display, without touching the
+    /// frame memory.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        if inverted {
+            self.send_command(&[0xa7]); //Inverse Display
+        } else {
+            self.send_command(&[0xa4]); //Normal Display
+        }
+    }
+
+    /// Changes how the gray levels are shown, without touching the frame
+    /// memory.
+    pub fn set_gray_scale_table(&mut self, table: GrayScaleTable) {
+        let pulse_widths = match table {
+            GrayScaleTable::Linear => {
+                self.send_command(&[0xb9]); //Select default linear gray scale table
+                return;
+            }
+            GrayScaleTable::Gamma22 => GAMMA_22,
+            GrayScaleTable::Custom(pulse_widths) => pulse_widths.pulse_widths(),
+        };
+        let mut command = [0xb8; 16]; //Set gray scale table
+        command[1..].copy_from_slice(&pulse_widths);
+        self.send_command(&command);
+    }
+
+    /// Continuously scrolls the given area to the left or right. This runs
+    /// completely on the controller, so no more writes are needed until
+    /// [`OledDisplay::stop_scroll`] is called or the frame memory is written
+    /// to, which stops the scroll as well.
+    pub fn start_horizontal_scroll(
+        &mut self,
+        direction: ScrollDirection,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        interval: ScrollInterval,
+    ) {
+        if x >= Self::WIDTH || y >= Self::HEIGHT || width == 0 || height == 0 {
+            return;
+        }
+        let x_end = (x + width).min(Self::WIDTH) - 1;
+        let y_end = (y + height).min(Self::HEIGHT) - 1;
+        let interval = match interval {
+            ScrollInterval::Frames2 => 0b111,
+            ScrollInterval::Frames3 => 0b100,
+            ScrollInterval::Frames4 => 0b101,
+            ScrollInterval::Frames5 => 0b110,
+            ScrollInterval::Frames64 => 0b010,
+            ScrollInterval::Frames128 => 0b011,
+        };
+        let command = match direction {
+            ScrollDirection::Right => 0x26,
+            ScrollDirection::Left => 0x27,
+        };
+
+        // The scroll has to be deactivated before it is set up again.
+        self.stop_scroll();
+        self.send_command(&[
+            command,
+            0x00,
+            y as u8,
+            interval,
+            y_end as u8,
+            (x / 2) as u8,
+            (x_end / 2) as u8,
+            0x00,
+        ]);
+        self.send_command(&[0x2f]); //Activate scroll
+        self.scrolling = true;
+    }
+
+    /// Stops a running scroll. The content of the scrolled area is
+    /// undefined afterwards and has to be written again.
+    pub fn stop_scroll(&mut self) {
+        self.send_command(&[0x2e]); //Deactivate scroll
+        self.scrolling = false;
+    }
+
+    fn send_command(&mut self, command_and_args: &[u8]) {
         self.data_command_pin
             .set_low()
             .unwrap_or_else(|_| todo!("Implement good panic"));
         self.chip_select_pin.set_low().unwrap();
-        self.spi.transfer(command_and_args).unwrap();
+        self.spi.write(command_and_args).unwrap();
         self.chip_select_pin.set_high().unwrap();
     }
 
-    fn send_data(&mut self, data: &mut [u8]) {
-        self.data_command_pin
-            .set_high()
-            .unwrap_or_else(|_| todo!("Implement good panic"));
+    //Sends the bytes as they are produced, chip select stays low for the whole burst
+    fn send_data_from(&mut self, data: impl IntoIterator<Item = u8>) {
+        let _ = self.data_command_pin.set_high();
         self.chip_select_pin.set_low().unwrap();
-        self.spi.transfer(data).unwrap();
+        for byte in data {
+            self.spi.write(&[byte]).unwrap();
+        }
         self.chip_select_pin.set_high().unwrap();
     }
 
-    fn set_frame_memory_from_raw(
-        &mut self,
-        image_buffer: &mut [u8],
-        mut x: usize,
+    /// Clips the given area to the display. Returns the first and last column
+    /// and row of the area, where each column holds two pixels.
+    fn clip(
+        x: usize,
         y: usize,
-        mut image_width: usize,
+        image_width: usize,
         image_height: usize,
-    ) {
-        x /= 2;
-        image_width /= 2;
+    ) -> Option<(usize, usize, usize, usize)> {
+        let x = x / 2;
+        let image_width = image_width / 2;
+
+        if x >= Self::WIDTH / 2 || y >= Self::HEIGHT || image_width == 0 || image_height == 0 {
+            return None;
+        }
 
         let x_end = if x + image_width >= Self::WIDTH / 2 {
             Self::WIDTH / 2 - 1
@@ -127,15 +272,31 @@ impl<DCPin: OutputPin, CSPin: port::PinOps> OledDisplay<DCPin, CSPin> {
         } else {
             y + image_height - 1
         };
+        Some((x, y, x_end, y_end))
+    }
 
-        self.set_memory_area(x, y, x_end, y_end);
-        self.send_data(image_buffer);
+    //Writes the area line by line in a single burst, the columns and rows are already clipped
+    fn write_scanlines(
+        &mut self,
+        mut f: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        x_end: usize,
+        y_end: usize,
+    ) {
+        let bytes_per_line = x_end - x + 1;
 
+        self.set_memory_area(x, y, x_end, y_end);
+        self.send_data_from((y..=y_end).flat_map(|line| {
+            let mut buffer = [0; LINE_BUFFER_SIZE];
+            f(line, &mut buffer[..bytes_per_line]);
+            buffer.into_iter().take(bytes_per_line)
+        }));
     }
 
     fn set_memory_area(&mut self, x: usize, y: usize, x_end: usize, y_end: usize) {
-        self.send_command(&mut [0x15, x as u8, x_end as u8]);
-        self.send_command(&mut [0x75, y as u8, y_end as u8]);
+        self.send_command(&[0x15, x as u8, x_end as u8]);
+        self.send_command(&[0x75, y as u8, y_end as u8]);
     }
 
     pub fn display_frame(&mut self) {
@@ -146,48 +307,155 @@ impl<DCPin: OutputPin, CSPin: port::PinOps> OledDisplay<DCPin, CSPin> {
     }
 }
 
-impl<DCPin: OutputPin, CSPin: port::PinOps> Display<u8> for OledDisplay<DCPin, CSPin> {
+impl<DCPin: OutputPin, CSPin: port::PinOps> Display<Gray4> for OledDisplay<DCPin, CSPin> {
     fn set_frame_memory_from_callback(
         &mut self,
-        f: impl Fn(usize, usize) -> u8,
-        mut x: usize,
+        f: impl Fn(usize, usize) -> Gray4,
+        x: usize,
         y: usize,
-        mut image_width: usize,
+        image_width: usize,
         image_height: usize,
     ) {
-        x /= 2;
-        image_width /= 2;
+        if self.scrolling {
+            self.stop_scroll();
+        }
 
-        if x > Self::WIDTH / 2 || y > Self::HEIGHT || image_width == 0 || image_height == 0 {
-            return;
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
+        };
+
+        self.write_scanlines(
+            |line, buffer| {
+                for (cursor, byte) in (x..=x_end).zip(buffer.iter_mut()) {
+                    *byte = f(cursor * 2, line).level() << 4 | f(cursor * 2 + 1, line).level();
+                }
+            },
+            x,
+            y,
+            x_end,
+            y_end,
+        );
+    }
+
+    /// A line is a row of pixels with four bits per pixel, the left pixel of
+    /// each byte in the high nibble. The area starts at an even column.
+    fn set_frame_memory_from_scanlines(
+        &mut self,
+        cb: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        if self.scrolling {
+            self.stop_scroll();
         }
 
-        let x_end = if x + image_width >= Self::WIDTH / 2 {
-            Self::WIDTH / 2 - 1
-        } else {
-            x + image_width - 1
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
         };
-        let y_end = if y + image_height >= Self::HEIGHT {
-            Self::HEIGHT - 1
-        } else {
-            y + image_height - 1
+
+        self.write_scanlines(cb, x, y, x_end, y_end);
+    }
+
+    /// The lines are sent straight from the buffer, without copying them.
+    fn set_frame_memory_from_raw(
+        &mut self,
+        buffer: &(impl ImageData + ?Sized),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        if self.scrolling {
+            self.stop_scroll();
+        }
+
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
         };
+        let bytes_per_line = Packed4Bpp::bytes_per_line(image_width);
+        let bytes = x_end - x + 1;
 
         self.set_memory_area(x, y, x_end, y_end);
+        self.send_data_from((0..=y_end - y).flat_map(|line| {
+            let start = line * bytes_per_line;
+            (start..start + bytes).map(move |index| buffer.byte(index))
+        }));
+    }
 
-        for line in y..=y_end {
-            for cursor in x..=x_end {
-                let byte = f(cursor * 2, line) << 4 | (f(cursor * 2 + 1, line) & 0x0F);
-                self.send_data(&mut [byte]);
-            }
+    fn set_frame_memory_from_bitmap1(
+        &mut self,
+        bitmap: &Bitmap1<impl ImageData + ?Sized>,
+        x: usize,
+        y: usize,
+        color: Gray4,
+        bg_color: Gray4,
+    ) {
+        let level = |set| if set { color.level() } else { bg_color.level() };
+        self.set_frame_memory_from_scanlines(
+            |line, buffer| {
+                for (cursor, byte) in buffer.iter_mut().enumerate() {
+                    *byte = level(bitmap.pixel(cursor * 2, line - y)) << 4
+                        | level(bitmap.pixel(cursor * 2 + 1, line - y));
+                }
+            },
+            x,
+            y,
+            bitmap.width(),
+            bitmap.height(),
+        );
+    }
+
+    /// The bitmap already has the layout of the display, so only the levels
+    /// are mapped through the palette, which is called once for each level.
+    fn set_frame_memory_from_bitmap4(
+        &mut self,
+        bitmap: &Bitmap4<impl ImageData + ?Sized>,
+        x: usize,
+        y: usize,
+        palette: impl Fn(Gray4) -> Gray4,
+    ) {
+        let mut levels = [0; 16];
+        for (level, mapped) in levels.iter_mut().enumerate() {
+            *mapped = palette(Gray4::new(level as u8).unwrap()).level();
         }
+
+        self.set_frame_memory_from_scanlines(
+            |line, buffer| {
+                for (byte, pixels) in buffer.iter_mut().zip(bitmap.row(line - y)) {
+                    *byte = levels[(pixels >> 4) as usize] << 4 | levels[(pixels & 0x0F) as usize];
+                }
+            },
+            x,
+            y,
+            bitmap.width(),
+            bitmap.height(),
+        );
     }
 
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Gray4) {
+        if self.scrolling {
+            self.stop_scroll();
+        }
+
+        let (x, y, x_end, y_end) = match Self::clip(x, y, width, height) {
+            Some(area) => area,
+            None => return,
+        };
 
+        self.set_memory_area(x, y, x_end, y_end);
+        let byte = color.level() << 4 | color.level();
+        self.send_data_from(iter::repeat(byte).take((x_end - x + 1) * (y_end - y + 1)));
+    }
 
     const PIXEL_PER_BYTE: usize = 2;
+    type NativeFormat = Packed4Bpp;
     const HEIGHT: usize = 128;
     const WIDTH: usize = 128;
-    const DARK_COLOR: u8 = 0x0;
-    const LIGHT_COLOR: u8 = 0xF;
+    const DARK_COLOR: Gray4 = Gray4::BLACK;
+    const LIGHT_COLOR: Gray4 = Gray4::WHITE;
 }
@@ -1,11 +1,18 @@
 use arduino_hal::{
     port,
-    prelude::_embedded_hal_blocking_spi_Transfer,
+    prelude::_embedded_hal_blocking_spi_Write,
     spi::{self, ChipSelectPin},
 };
+use core::{iter, marker::PhantomData};
+
 use embedded_hal::digital::v2::OutputPin;
 
-use super::Display;
+use super::{
+    BitOrder, Bitmap1, Display, ImageData, ScrollDirection, ScrollInterval, VerticalPage1Bpp,
+};
+
+// -- The columns of the widest panel
+const LINE_BUFFER_SIZE: usize = 128;
 
 const OLED_SPI_SETTINGS: spi::Settings = spi::Settings {
     data_order: spi::DataOrder::MostSignificantFirst,
@@ -13,19 +20,124 @@ const OLED_SPI_SETTINGS: spi::Settings = spi::Settings {
     mode: embedded_hal::spi::MODE_0,
 };
 
-pub struct WideOledDisplay<DCPin: OutputPin, CSPin: port::PinOps> {
+/// The geometry of a panel driven by the SSD1306.
+pub trait WideOledSize {
+    /// The width of the panel in pixel.
+    const WIDTH: usize;
+    /// The height of the panel in pixel, which is also the multiplex ratio.
+    const HEIGHT: usize;
+    /// The COM pins hardware configuration, command 0xDA.
+    const COM_PINS: u8;
+    /// The first row connected to the panel, command 0xD3.
+    const DISPLAY_OFFSET: u8 = 0;
+    /// The first column connected to the panel.
+    const COLUMN_OFFSET: usize = 0;
+}
+
+pub struct Size128x64;
+
+impl WideOledSize for Size128x64 {
+    const WIDTH: usize = 128;
+    const HEIGHT: usize = 64;
+    const COM_PINS: u8 = 0x12;
+}
+
+pub struct Size128x32;
+
+impl WideOledSize for Size128x32 {
+    const WIDTH: usize = 128;
+    const HEIGHT: usize = 32;
+    const COM_PINS: u8 = 0x02;
+}
+
+pub struct Size96x16;
+
+impl WideOledSize for Size96x16 {
+    const WIDTH: usize = 96;
+    const HEIGHT: usize = 16;
+    const COM_PINS: u8 = 0x02;
+}
+
+pub struct Size64x48;
+
+impl WideOledSize for Size64x48 {
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+    const COM_PINS: u8 = 0x12;
+    const COLUMN_OFFSET: usize = 32;
+}
+
+pub struct Size72x40;
+
+impl WideOledSize for Size72x40 {
+    const WIDTH: usize = 72;
+    const HEIGHT: usize = 40;
+    const COM_PINS: u8 = 0x12;
+    const COLUMN_OFFSET: usize = 28;
+}
+
+/// How the controller moves through the frame memory while it is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AddressingMode {
+    /// Column by column and then to the next page, inside of a window.
+    Horizontal,
+    /// Page by page and then to the next column, inside of a window.
+    Vertical,
+    /// Column by column inside of a single page.
+    Page,
+}
+
+/// The effect started by [`WideOledDisplay::start_fade`].
+#[derive(Clone, Copy, Debug)]
+pub enum FadeMode {
+    /// The display fades out once and stays dark.
+    FadeOut,
+    /// The display fades out and in again over and over.
+    Blink,
+}
+
+/// The time between two brightness steps of a fade, which is a multiple of 8
+/// frames between 8 and 128 frames.
+#[derive(Clone, Copy, Debug)]
+pub struct FadeInterval(u8);
+
+#[allow(dead_code)]
+impl FadeInterval {
+    /// Rounds down to the next supported interval.
+    pub fn from_frames(frames: u8) -> FadeInterval {
+        FadeInterval((frames / 8).clamp(1, 16) - 1)
+    }
+
+    pub fn frames(self) -> u8 {
+        (self.0 + 1) * 8
+    }
+}
+
+pub struct WideOledDisplay<
+    DCPin: OutputPin,
+    CSPin: port::PinOps,
+    Size: WideOledSize = Size128x64,
+> {
     data_command_pin: DCPin,
     chip_select_pin: ChipSelectPin<CSPin>,
     spi: arduino_hal::Spi,
+    scrolling: bool,
+    fading: bool,
+    zoomed: bool,
+    addressing_mode: AddressingMode,
+    size: PhantomData<Size>,
 }
 
 #[allow(dead_code)]
-impl<DCPin: OutputPin, CSPin: port::PinOps> WideOledDisplay<DCPin, CSPin> {
+impl<DCPin: OutputPin, CSPin: port::PinOps, Size: WideOledSize>
+    WideOledDisplay<DCPin, CSPin, Size>
+{
     pub fn new(
         mut spi: arduino_hal::Spi,
         mut chip_select_pin: ChipSelectPin<CSPin>,
         mut reset_pin: impl OutputPin,
         data_command_pin: DCPin,
+        _size: Size,
     ) -> Self {
         spi.reconfigure(OLED_SPI_SETTINGS).unwrap();
 
@@ -40,66 +152,372 @@ impl<DCPin: OutputPin, CSPin: port::PinOps> WideOledDisplay<DCPin, CSPin> {
             data_command_pin,
             spi,
             chip_select_pin,
+            scrolling: false,
+            fading: false,
+            zoomed: false,
+            addressing_mode: AddressingMode::Horizontal,
+            size: PhantomData,
         };
 
-        result.send_command(&mut [
+        result.send_command(&[
+            0xae, // -- Turn oled panel off
+            0xd5,
+            0x80, // -- Set display clock divide ratio and oscillator frequency
+            0xa8,
+            (Size::HEIGHT - 1) as u8, // -- Set multiplex ratio
+            0xd3,
+            Size::DISPLAY_OFFSET, // -- Set display offset
+            0x40, // -- Set display start line to 0
+            0x8d,
+            0x14, // -- Enable charge pump
             0x20,
             0x00, // -- Set horizontal addressing mode
             0xa1, // -- Turn Display upside down
             0xc8, // -- Flip Display horizontally
+            0xda,
+            Size::COM_PINS, // -- Set COM pins hardware configuration
+            0x81,
+            0xcf, // -- Set contrast
+            0xd9,
+            0xf1, // -- Set pre-charge period
+            0xdb,
+            0x40, // -- Set VCOMH deselect level
+            0xa4, // -- Show the frame memory
+            0xa6, // -- Normal display
+            0x2e, // -- Deactivate scroll
             0xaf, // -- turn on oled panel
         ]);
         result
     }
 
-    fn send_command(&mut self, commands: &mut [u8]) {
-        let _ = self.data_command_pin.set_low();
-        self.chip_select_pin.set_low().unwrap();
-        self.spi.transfer(commands).unwrap();
-        self.chip_select_pin.set_high().unwrap();
+    /// Turns the panel off. The frame memory can still be written and is
+    /// shown after [`WideOledDisplay::wake`].
+    pub fn sleep(&mut self) {
+        self.send_command(&[0xae]);
     }
 
-    fn send_data(&mut self, data: &mut [u8]) {
-        let _ = self.data_command_pin.set_high();
-        self.chip_select_pin.set_low().unwrap();
-        self.spi.transfer(data).unwrap();
-        self.chip_select_pin.set_high().unwrap();
+    /// Turns the panel back on after [`WideOledDisplay::sleep`].
+    pub fn wake(&mut self) {
+        self.send_command(&[0xaf]);
     }
 
-    fn set_frame_memory_from_raw(
+    /// Inverts the colors of the whole display, without touching the frame
+    /// memory.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        if inverted {
+            self.send_command(&[0xa7]); // -- Inverse display
+        } else {
+            self.send_command(&[0xa6]); // -- Normal display
+        }
+    }
+
+    /// Continuously scrolls the pages between `y` and `y + height` to the
+    /// left or right. This runs completely on the controller, so no more
+    /// writes are needed until [`WideOledDisplay::stop_scroll`] is called or
+    /// the frame memory is written to, which stops the scroll as well.
+    pub fn start_horizontal_scroll(
+        &mut self,
+        direction: ScrollDirection,
+        y: usize,
+        height: usize,
+        interval: ScrollInterval,
+    ) {
+        let (start_page, end_page) = match Self::scroll_pages(y, height) {
+            Some(pages) => pages,
+            None => return,
+        };
+        // The scroll has to be deactivated before it is set up again.
+        self.stop_scroll();
+        let command = match direction {
+            ScrollDirection::Right => 0x26,
+            ScrollDirection::Left => 0x27,
+        };
+        self.send_command(&[
+            command,
+            0x00,
+            start_page,
+            Self::scroll_interval(interval),
+            end_page,
+            0x00,
+            0xff,
+        ]);
+        self.send_command(&[0x2f]); // -- Activate scroll
+        self.scrolling = true;
+    }
+
+    /// Like [`WideOledDisplay::start_horizontal_scroll`], but the whole
+    /// display additionally moves up by `vertical_offset` rows on every step.
+    pub fn start_diagonal_scroll(
         &mut self,
-        image_buffer: &mut [u8],
+        direction: ScrollDirection,
+        y: usize,
+        height: usize,
+        vertical_offset: u8,
+        interval: ScrollInterval,
+    ) {
+        let (start_page, end_page) = match Self::scroll_pages(y, height) {
+            Some(pages) => pages,
+            None => return,
+        };
+        self.stop_scroll();
+        // -- Set the vertical scroll area to the whole display
+        self.send_command(&[0xa3, 0x00, Self::HEIGHT as u8]);
+        let command = match direction {
+            ScrollDirection::Right => 0x29,
+            ScrollDirection::Left => 0x2a,
+        };
+        self.send_command(&[
+            command,
+            0x00,
+            start_page,
+            Self::scroll_interval(interval),
+            end_page,
+            vertical_offset % Self::HEIGHT as u8,
+        ]);
+        self.send_command(&[0x2f]); // -- Activate scroll
+        self.scrolling = true;
+    }
+
+    /// Stops a running scroll. The content of the scrolled area is
+    /// undefined afterwards and has to be written again.
+    pub fn stop_scroll(&mut self) {
+        self.send_command(&[0x2e]); // -- Deactivate scroll
+        self.scrolling = false;
+    }
+
+    /// Fades the display out or lets it blink. Like scrolling, this runs on
+    /// the controller until [`WideOledDisplay::stop_fade`] is called or the
+    /// frame memory is written to.
+    pub fn start_fade(&mut self, mode: FadeMode, interval: FadeInterval) {
+        let mode = match mode {
+            FadeMode::FadeOut => 0x20,
+            FadeMode::Blink => 0x30,
+        };
+        self.send_command(&[0x23, mode | interval.0]); // -- Set fade out or blinking
+        self.fading = true;
+    }
+
+    /// Stops a fade or blinking and shows the display at full brightness.
+    pub fn stop_fade(&mut self) {
+        self.send_command(&[0x23, 0x00]); // -- Disable fade out and blinking
+        self.fading = false;
+    }
+
+    /// Shows the upper half of the display at twice the height. This only
+    /// works with panels using the alternative COM pins configuration, like
+    /// [`Size128x64`]. The zoom is disabled as soon as the frame memory is
+    /// written to.
+    pub fn set_zoom(&mut self, zoomed: bool) {
+        self.send_command(&[0xd6, zoomed as u8]); // -- Set zoom in
+        self.zoomed = zoomed;
+    }
+
+    /// Stops all effects running on the controller, which would otherwise
+    /// distort the frame memory written next.
+    fn stop_effects(&mut self) {
+        if self.scrolling {
+            self.stop_scroll();
+        }
+        if self.fading {
+            self.stop_fade();
+        }
+        if self.zoomed {
+            self.set_zoom(false);
+        }
+    }
+
+    fn scroll_pages(y: usize, height: usize) -> Option<(u8, u8)> {
+        if height == 0 || y >= Self::HEIGHT {
+            return None;
+        }
+        let end = (y + height).min(Self::HEIGHT) - 1;
+        Some(((y / 8) as u8, (end / 8) as u8))
+    }
+
+    fn scroll_interval(interval: ScrollInterval) -> u8 {
+        match interval {
+            ScrollInterval::Frames2 => 0b111,
+            ScrollInterval::Frames3 => 0b100,
+            ScrollInterval::Frames4 => 0b101,
+            ScrollInterval::Frames5 => 0b000,
+            ScrollInterval::Frames64 => 0b001,
+            ScrollInterval::Frames128 => 0b010,
+        }
+    }
+
+    /// Clips the given area to the display. Returns the first and last column
+    /// and page of the area, or `None` if not a single page of it is on the
+    /// display.
+    fn clip(
         x: usize,
-        mut y: usize,
+        y: usize,
         image_width: usize,
-        mut image_height: usize,
-    ) {
-        y /= 8;
-        image_height /= 8;
+        image_height: usize,
+    ) -> Option<(usize, usize, usize, usize)> {
+        let page = y / 8;
+        let pages = image_height / 8;
 
-        let x_end = if x + image_width >= Self::WIDTH {
-            Self::WIDTH - 1
+        if x >= Size::WIDTH || y >= Size::HEIGHT || image_width == 0 || pages == 0 {
+            return None;
+        }
+
+        let x_end = if x + image_width >= Size::WIDTH {
+            Size::WIDTH - 1
         } else {
             x + image_width - 1
         };
-        let y_end = if y + image_height >= Self::HEIGHT {
-            Self::HEIGHT - 1
+        let page_end = if page + pages >= Size::HEIGHT / 8 {
+            Size::HEIGHT / 8 - 1
+        } else {
+            page + pages - 1
+        };
+        Some((x, page, x_end, page_end))
+    }
+
+    /// Writes the given area, where `byte` returns the 8 vertical pixels of a
+    /// column in a page. The addressing mode is chosen, so that the least
+    /// commands are needed to set up the area.
+    fn write_area(
+        &mut self,
+        x: usize,
+        page: usize,
+        x_end: usize,
+        page_end: usize,
+        byte: impl Fn(usize, usize) -> u8,
+    ) {
+        let columns = x_end - x + 1;
+        let pages = page_end - page + 1;
+
+        match self.addressing_mode_for(columns, pages) {
+            AddressingMode::Vertical => {
+                self.set_addressing_mode(AddressingMode::Vertical);
+                self.set_window(x, page, x_end, page_end);
+                let byte = &byte;
+                self.send_data_from(
+                    (x..=x_end).flat_map(|x| (page..=page_end).map(move |page| byte(x, page))),
+                );
+            }
+            addressing_mode => self.write_pages(
+                addressing_mode,
+                |page, buffer| {
+                    for (x, column) in (x..=x_end).zip(buffer.iter_mut()) {
+                        *column = byte(x, page);
+                    }
+                },
+                x,
+                page,
+                x_end,
+                page_end,
+            ),
+        }
+    }
+
+    /// Writes the given area page by page, where `f` fills the columns of a
+    /// page. This works with horizontal and page addressing.
+    fn write_pages(
+        &mut self,
+        addressing_mode: AddressingMode,
+        mut f: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        page: usize,
+        x_end: usize,
+        page_end: usize,
+    ) {
+        let columns = x_end - x + 1;
+        let mut page_bytes = |page| {
+            let mut buffer = [0; LINE_BUFFER_SIZE];
+            f(page, &mut buffer[..columns]);
+            buffer.into_iter().take(columns)
+        };
+
+        self.set_addressing_mode(addressing_mode);
+        match addressing_mode {
+            AddressingMode::Page => {
+                let column = Size::COLUMN_OFFSET + x;
+                for page in page..=page_end {
+                    self.send_command(&[
+                        0xb0 | page as u8,          // -- Set page start address
+                        (column & 0x0f) as u8,      // -- Set lower column start address
+                        0x10 | (column >> 4) as u8, // -- Set higher column start address
+                    ]);
+                    self.send_data_from(page_bytes(page));
+                }
+            }
+            _ => {
+                self.set_window(x, page, x_end, page_end);
+                self.send_data_from((page..=page_end).flat_map(page_bytes));
+            }
+        }
+    }
+
+    fn addressing_mode_for(&self, columns: usize, pages: usize) -> AddressingMode {
+        // Tall and narrow areas like a column of glyphs are written page by
+        // page, everything else column by column. Both need the same window.
+        let window_mode = if columns < pages {
+            AddressingMode::Vertical
         } else {
-            y + image_height - 1
+            AddressingMode::Horizontal
         };
+        let switch_cost = |mode: AddressingMode| if mode == self.addressing_mode { 0 } else { 2 };
+        // The window takes 6 bytes of commands, while page addressing needs 3
+        // bytes for every page.
+        let window_cost = 6 + switch_cost(window_mode);
+        let page_cost = 3 * pages + switch_cost(AddressingMode::Page);
+        if page_cost <= window_cost {
+            AddressingMode::Page
+        } else {
+            window_mode
+        }
+    }
 
-        self.send_command(&mut [0x21, x as u8, x_end as u8]);
-        self.send_command(&mut [0x22, y as u8, y_end as u8]);
-        for line in y..=y_end {
-            self.send_data(&mut image_buffer[(line - y) * image_width / Self::PIXEL_PER_BYTE..][..image_width]);
+    fn set_addressing_mode(&mut self, addressing_mode: AddressingMode) {
+        if addressing_mode == self.addressing_mode {
+            return;
         }
+        let mode = match addressing_mode {
+            AddressingMode::Horizontal => 0x00,
+            AddressingMode::Vertical => 0x01,
+            AddressingMode::Page => 0x02,
+        };
+        self.send_command(&[0x20, mode]); // -- Set memory addressing mode
+        self.addressing_mode = addressing_mode;
+    }
+
+    fn set_window(&mut self, x: usize, page: usize, x_end: usize, page_end: usize) {
+        self.send_command(&[
+            0x21,
+            (Size::COLUMN_OFFSET + x) as u8,
+            (Size::COLUMN_OFFSET + x_end) as u8,
+        ]);
+        self.send_command(&[0x22, page as u8, page_end as u8]);
+    }
+
+    fn send_command(&mut self, commands: &[u8]) {
+        let _ = self.data_command_pin.set_low();
+        self.chip_select_pin.set_low().unwrap();
+        self.spi.write(commands).unwrap();
+        self.chip_select_pin.set_high().unwrap();
+    }
+
+    // -- Sends the bytes as they are produced, chip select stays low for the
+    // -- whole burst
+    fn send_data_from(&mut self, data: impl IntoIterator<Item = u8>) {
+        let _ = self.data_command_pin.set_high();
+        self.chip_select_pin.set_low().unwrap();
+        for byte in data {
+            self.spi.write(&[byte]).unwrap();
+        }
+        self.chip_select_pin.set_high().unwrap();
     }
 }
 
-impl<DCPin: OutputPin, CSPin: port::PinOps> Display<bool> for WideOledDisplay<DCPin, CSPin> {
+impl<DCPin: OutputPin, CSPin: port::PinOps, Size: WideOledSize> Display<bool>
+    for WideOledDisplay<DCPin, CSPin, Size>
+{
     const PIXEL_PER_BYTE: usize = 8;
-    const WIDTH: usize = 128;
-    const HEIGHT: usize = 64;
+    type NativeFormat = VerticalPage1Bpp;
+    const WIDTH: usize = Size::WIDTH;
+    const HEIGHT: usize = Size::HEIGHT;
     const LIGHT_COLOR: bool = true;
     const DARK_COLOR: bool = false;
 
@@ -107,35 +525,137 @@ impl<DCPin: OutputPin, CSPin: port::PinOps> Display<bool> for WideOledDisplay<DC
         &mut self,
         cb: impl Fn(usize, usize) -> bool,
         x: usize,
-        mut y: usize,
+        y: usize,
         image_width: usize,
-        mut image_height: usize,
+        image_height: usize,
     ) {
-        y /= 8;
-        image_height /= 8;
+        self.stop_effects();
 
-        let x_end = if x + image_width >= Self::WIDTH {
-            Self::WIDTH - 1
-        } else {
-            x + image_width - 1
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
         };
-        let y_end = if y + image_height >= Self::HEIGHT {
-            Self::HEIGHT - 1
-        } else {
-            y + image_height - 1
+        self.write_area(x, y, x_end, y_end, |x, page| {
+            let mut byte = 0;
+            for y in (0..8).rev() {
+                byte <<= 1;
+                byte |= cb(x, page * 8 + y) as u8
+            }
+            byte
+        });
+    }
+
+    /// A line is a page of 8 rows, with a byte for each column and the top
+    /// pixel in the least significant bit. So the callback gets the page
+    /// instead of the row and the area is aligned to whole pages.
+    fn set_frame_memory_from_scanlines(
+        &mut self,
+        cb: impl FnMut(usize, &mut [u8]),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        self.stop_effects();
+
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
+        };
+        let addressing_mode = match self.addressing_mode_for(x_end - x + 1, y_end - y + 1) {
+            // -- Vertical addressing would need all pages of a column at once
+            AddressingMode::Vertical => AddressingMode::Horizontal,
+            addressing_mode => addressing_mode,
         };
+        self.write_pages(addressing_mode, cb, x, y, x_end, y_end);
+    }
 
-        self.send_command(&mut [0x21, x as u8, x_end as u8]);
-        self.send_command(&mut [0x22, y as u8, y_end as u8]);
-        for line in y..=y_end {
-            for x in x..=x_end {
-                let mut byte = 0;
-                for y in (0..8).rev() {
-                    byte <<= 1;
-                    byte |= cb(x, line * 8 + y) as u8
+    /// Unlike the scanlines, this can use any addressing mode.
+    fn set_frame_memory_from_raw(
+        &mut self,
+        buffer: &(impl ImageData + ?Sized),
+        x: usize,
+        y: usize,
+        image_width: usize,
+        image_height: usize,
+    ) {
+        self.stop_effects();
+
+        let (x, y, x_end, y_end) = match Self::clip(x, y, image_width, image_height) {
+            Some(area) => area,
+            None => return,
+        };
+        self.write_area(x, y, x_end, y_end, |column, page| {
+            buffer.byte((page - y) * image_width + column - x)
+        });
+    }
+
+    /// The rows of the bitmap are read a byte at a time and each page is put
+    /// together from eight of them.
+    fn set_frame_memory_from_bitmap1(
+        &mut self,
+        bitmap: &Bitmap1<impl ImageData + ?Sized>,
+        x: usize,
+        y: usize,
+        color: bool,
+        bg_color: bool,
+    ) {
+        self.stop_effects();
+
+        // -- The pages have to cover every row of the bitmap, also when it
+        // -- doesn't start or end on a page
+        let page_rows = match bitmap.height() {
+            0 => 0,
+            height => (y % 8 + height + 7) / 8 * 8,
+        };
+        let (x, page, x_end, page_end) = match Self::clip(x, y, bitmap.width(), page_rows) {
+            Some(area) => area,
+            None => return,
+        };
+        let addressing_mode = match self.addressing_mode_for(x_end - x + 1, page_end - page + 1) {
+            // -- Every page needs eight whole rows of the bitmap
+            AddressingMode::Vertical => AddressingMode::Horizontal,
+            addressing_mode => addressing_mode,
+        };
+        let bit_order = bitmap.bit_order();
+        self.write_pages(addressing_mode, |page, buffer| {
+            for bit in 0..8 {
+                let row = page * 8 + bit;
+                if row < y || row - y >= bitmap.height() {
+                    // -- The page reaches past the bitmap
+                    if bg_color {
+                        buffer.iter_mut().for_each(|column| *column |= 1 << bit);
+                    }
+                    continue;
+                }
+                let pixels = bitmap.row(row - y).flat_map(|byte| {
+                    (0..8).map(move |index| match bit_order {
+                        BitOrder::MsbFirst => byte & (0x80 >> index) != 0,
+                        BitOrder::LsbFirst => byte & (1 << index) != 0,
+                    })
+                });
+                for (column, pixel) in buffer.iter_mut().zip(pixels) {
+                    let on = if pixel { color } else { bg_color };
+                    *column |= (on as u8) << bit;
                 }
-                self.send_data(&mut [byte]);
             }
+        }, x, page, x_end, page_end);
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: bool) {
+        self.stop_effects();
+
+        let (x, y, x_end, y_end) = match Self::clip(x, y, width, height) {
+            Some(area) => area,
+            None => return,
+        };
+        // The write order doesn't matter for a single color, so either window
+        // mode will do. Only page addressing can't stream a whole area.
+        if self.addressing_mode == AddressingMode::Page {
+            self.set_addressing_mode(AddressingMode::Horizontal);
         }
+        self.set_window(x, y, x_end, y_end);
+        let byte = if color { 0xff } else { 0x00 };
+        self.send_data_from(iter::repeat(byte).take((x_end - x + 1) * (y_end - y + 1)));
     }
 }
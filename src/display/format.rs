@@ -0,0 +1,64 @@
+/// Describes how a display packs pixels into the bytes it takes. The bytes
+/// are grouped into lines, which follow each other without gaps.
+pub trait NativeFormat {
+    /// The number of bytes in a line of the given width in pixel
+    fn bytes_per_line(width: usize) -> usize;
+    /// The line which holds the given row of pixels
+    fn line_of(y: usize) -> usize;
+}
+
+/// A line is a row of pixels with one bit per pixel. The leftmost pixel is in
+/// the most significant bit and every line starts with a new byte.
+pub struct HorizontalMsbFirst1Bpp;
+
+impl NativeFormat for HorizontalMsbFirst1Bpp {
+    fn bytes_per_line(width: usize) -> usize {
+        (width + 7) / 8
+    }
+
+    fn line_of(y: usize) -> usize {
+        y
+    }
+}
+
+/// A line is a page of 8 rows, with one byte for each column. The top pixel
+/// of a column is in the least significant bit.
+pub struct VerticalPage1Bpp;
+
+impl NativeFormat for VerticalPage1Bpp {
+    fn bytes_per_line(width: usize) -> usize {
+        width
+    }
+
+    fn line_of(y: usize) -> usize {
+        y / 8
+    }
+}
+
+/// A line is a row of pixels with two bits per pixel. The leftmost pixel is
+/// in the most significant bits and every line starts with a new byte.
+pub struct Packed2Bpp;
+
+impl NativeFormat for Packed2Bpp {
+    fn bytes_per_line(width: usize) -> usize {
+        (width + 3) / 4
+    }
+
+    fn line_of(y: usize) -> usize {
+        y
+    }
+}
+
+/// A line is a row of pixels with four bits per pixel. The left pixel of a
+/// byte is in the high nibble and every line starts with a new byte.
+pub struct Packed4Bpp;
+
+impl NativeFormat for Packed4Bpp {
+    fn bytes_per_line(width: usize) -> usize {
+        (width + 1) / 2
+    }
+
+    fn line_of(y: usize) -> usize {
+        y
+    }
+}
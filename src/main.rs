@@ -75,6 +75,7 @@ fn main() -> ! {
         pins.d8.into_output(),
         pins.d9.into_output(),
         pins.d7.into_pull_up_input(),
+        Epd1in54,
     );
 
     println!("Inited!");
@@ -84,15 +85,18 @@ fn main() -> ! {
     d.set_frame_memory_from_callback(|x, y| ((x + y) * 16 / 256) > 7, 0, 0, d.width(), d.height());
     d.write_string("Hewwo!", 0, 0, d.light_color(), d.dark_color());
     d.display_frame();
+    d.set_refresh_mode(RefreshMode::Partial {
+        full_refresh_every: 60,
+    });
     println!("done");
     loop {
-        d.write_string(&text[..tick], 0, 8, d.light_color(), d.dark_color());
+        d.update_string(&text[..tick], 0, 8, d.light_color(), d.dark_color());
         arduino_hal::delay_ms(500);
         tick += 1;
         if tick > text.len() {
             d.set_frame_memory_from_callback(|x, y| ((x + y) * 16 / 256) > 7, 0, 8, d.width(), 8);
             tick = 0;
+            d.display_frame();
         }
-        d.display_frame();
     }
 }
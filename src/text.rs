@@ -2,24 +2,118 @@ use crate::display::Display;
 use core::fmt::Debug;
 mod ascii_font;
 
+pub use ascii_font::{Font8x8, BASIC_LEGACY};
+
+/// A font with glyphs of 8x8 pixels.
+pub trait Font {
+    /// Returns a row of the glyph for the given character, with the leftmost
+    /// pixel in the least significant bit.
+    fn glyph_row(&self, character: u8, row: usize) -> u8;
+}
+
 pub trait Writer<Color> {
     fn write_string(&mut self, text: &str, x: usize, y: usize, color: Color, bg_color: Color);
+
+    fn write_string_with_font(
+        &mut self,
+        font: &impl Font,
+        text: &str,
+        x: usize,
+        y: usize,
+        color: Color,
+        bg_color: Color,
+    );
+
+    fn update_string(&mut self, text: &str, x: usize, y: usize, color: Color, bg_color: Color);
+
+    fn update_string_with_font(
+        &mut self,
+        font: &impl Font,
+        text: &str,
+        x: usize,
+        y: usize,
+        color: Color,
+        bg_color: Color,
+    );
 }
 
 impl<Color: Copy + Debug, D: Display<Color>> Writer<Color> for D {
-    fn write_string(&mut self, text: &str, mut x: usize, y: usize, color: Color, bg_color: Color) {
-        let mut letter: [Color; 8 * 8];
-        for byte in text.bytes() {
-            letter = core::array::from_fn(|i| {
-                let byte = ascii_font::BASIC_LEGACY[byte as usize][i / 8];
-                if ((1 << (i % 8)) & byte) > 0 {
-                    color
-                } else {
-                    bg_color
-                }
-            });
-            self.set_frame_memory_from_buffer(&mut letter, x, y, 8, 8);
-            x += 8;
+    /// The text is written in the [`BASIC_LEGACY`] font with
+    /// [`Writer::write_string_with_font`].
+    fn write_string(&mut self, text: &str, x: usize, y: usize, color: Color, bg_color: Color) {
+        self.write_string_with_font(&BASIC_LEGACY, text, x, y, color, bg_color);
+    }
+
+    /// The text is only written to the frame memory, so several strings can
+    /// be shown with a single [`Display::display_frame`].
+    fn write_string_with_font(
+        &mut self,
+        font: &impl Font,
+        text: &str,
+        x: usize,
+        y: usize,
+        color: Color,
+        bg_color: Color,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+        self.set_frame_memory_from_callback(
+            glyph_pixels(font, text.as_bytes(), x, y, color, bg_color),
+            x,
+            y,
+            text.len() * 8,
+            8,
+        );
+    }
+
+    /// The text is written in the [`BASIC_LEGACY`] font with
+    /// [`Writer::update_string_with_font`].
+    fn update_string(&mut self, text: &str, x: usize, y: usize, color: Color, bg_color: Color) {
+        self.update_string_with_font(&BASIC_LEGACY, text, x, y, color, bg_color);
+    }
+
+    /// The text is written with [`Display::update_region`], so it is visible
+    /// right away. This is meant for small changes like a counter.
+    fn update_string_with_font(
+        &mut self,
+        font: &impl Font,
+        text: &str,
+        x: usize,
+        y: usize,
+        color: Color,
+        bg_color: Color,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+        self.update_region(
+            glyph_pixels(font, text.as_bytes(), x, y, color, bg_color),
+            x,
+            y,
+            text.len() * 8,
+            8,
+        );
+    }
+}
+
+/// Returns the color of every pixel of the text, when it is drawn at the given
+/// position.
+fn glyph_pixels<'a, Color: Copy + 'a>(
+    font: &'a impl Font,
+    text: &'a [u8],
+    x: usize,
+    y: usize,
+    color: Color,
+    bg_color: Color,
+) -> impl Fn(usize, usize) -> Color + 'a {
+    move |dx, dy| {
+        let dx = dx - x;
+        let row = font.glyph_row(text[dx / 8], dy - y);
+        if ((1 << (dx % 8)) & row) > 0 {
+            color
+        } else {
+            bg_color
         }
     }
 }
@@ -1,12 +1,48 @@
-/// TODO: Maybe we want to use progmem here, since this memory is never written
-/// to. But this means, we have to use static instead of const. I'm not sure, if
-/// the loss of compiler optimization would make this valuable.
+use avr_progmem::{progmem, wrapper::ProgMem};
 
+use super::Font;
+
+/// A font with 8x8 pixel glyphs for the 128 ASCII characters, which are
+/// stored in program memory, so they don't take up any RAM. Every row of a
+/// glyph is a byte, with the leftmost pixel in the least significant bit.
+pub struct Font8x8 {
+    glyphs: &'static ProgMem<[u8; 128 * 8]>,
+}
+
+impl Font for Font8x8 {
+    /// Characters outside of ASCII are blank.
+    fn glyph_row(&self, character: u8, row: usize) -> u8 {
+        if character >= 128 {
+            return 0;
+        }
+        self.glyphs.load_at(character as usize * 8 + row)
+    }
+}
+
+pub static BASIC_LEGACY: Font8x8 = Font8x8 {
+    glyphs: &BASIC_LEGACY_PROGMEM,
+};
+
+progmem! {
+    static progmem BASIC_LEGACY_PROGMEM: [u8; 128 * 8] = flatten(BASIC_LEGACY_GLYPHS);
+}
+
+/// Puts the rows of all glyphs after each other, so a single row can be
+/// loaded from program memory.
+const fn flatten(glyphs: [[u8; 8]; 128]) -> [u8; 128 * 8] {
+    let mut bytes = [0; 128 * 8];
+    let mut index = 0;
+    while index < 128 * 8 {
+        bytes[index] = glyphs[index / 8][index % 8];
+        index += 1;
+    }
+    bytes
+}
 
 /// Indicates all zeros, meaning nothing to render.
 pub const NOTHING_TO_DISPLAY: [u8; 8] = [0x00; 8];
 
-pub const BASIC_LEGACY: [[u8; 8]; 128] = [
+const BASIC_LEGACY_GLYPHS: [[u8; 8]; 128] = [
     NOTHING_TO_DISPLAY,
     NOTHING_TO_DISPLAY,
     NOTHING_TO_DISPLAY,